@@ -1,6 +1,7 @@
 #version 330 core
 in vec3 ourColor;
 in vec3 ourNormal;
+in vec3 ourTangent;
 in vec2 ourTexCoord;
 in vec3 fragPos;
 
@@ -9,15 +10,23 @@ out vec4 FragColor;
 uniform sampler2D textureSampler;
 uniform bool useTexture;
 
+// Tangent-space normal map (RGB = XYZ remapped to 0..1), linear data
+uniform sampler2D normalMap;
+uniform bool useNormalMap;
+
 uniform vec3 viewPos;
 
+// Added on top of the lit color to mark the selected object (zero otherwise)
+uniform vec3 highlightColor;
+
 // Material properties
 uniform vec3 material_ambient;
 uniform vec3 material_diffuse;
 uniform vec3 material_specular;
 uniform float material_shininess;
+uniform vec3 material_emissive; // Self-illumination, added unlit (can exceed 1.0 for bloom)
 
-#define MAX_LIGHTS 4
+#define MAX_LIGHTS 8
 uniform int numLights;
 
 struct Light {
@@ -26,6 +35,11 @@ struct Light {
     float constant;
     float linear;
     float quadratic;
+    // Spotlight cone as cosines of the inner/outer half-angles
+    // (point lights use -1/-2 so the cone covers everything)
+    vec3 direction;
+    float cutOff;
+    float outerCutOff;
 };
 
 uniform Light lights[MAX_LIGHTS];
@@ -45,13 +59,32 @@ vec3 calculatePointLight(Light light, vec3 normal, vec3 fragPos, vec3 viewDir, v
     vec3 diffuse = diff * material_diffuse * light.color;
     vec3 specular = spec * material_specular * light.color;
 
+    // Soft-edged spotlight cone; ambient is left alone so the area outside isn't black
+    float theta = dot(lightDir, normalize(-light.direction));
+    float spot = smoothstep(light.outerCutOff, light.cutOff, theta);
+
     ambient *= attenuation;
-    diffuse *= attenuation;
-    specular *= attenuation;
+    diffuse *= attenuation * spot;
+    specular *= attenuation * spot;
 
     return (ambient + diffuse + specular) * objectColor;
 }
 
+// Bend the surface normal by the normal map, using the tangent frame from the mesh
+vec3 applyNormalMap(vec3 normal) {
+    // Re-orthogonalize after interpolation; no usable tangent means no perturbation
+    vec3 tangent = ourTangent - normal * dot(normal, ourTangent);
+    if (dot(tangent, tangent) < 1e-8) {
+        return normal;
+    }
+    tangent = normalize(tangent);
+    vec3 bitangent = cross(normal, tangent);
+    mat3 TBN = mat3(tangent, bitangent, normal);
+
+    vec3 sampled = texture(normalMap, ourTexCoord).rgb * 2.0 - 1.0;
+    return normalize(TBN * sampled);
+}
+
 void main() {
     vec3 objectColor;
     if (useTexture) {
@@ -63,13 +96,17 @@ void main() {
     }
 
     vec3 norm = normalize(ourNormal);
+    if (useNormalMap) {
+        norm = applyNormalMap(norm);
+    }
     vec3 viewDir = normalize(viewPos - fragPos);
 
     vec3 result = vec3(0.0);
 
-    for (int i = 0; i < MAX_LIGHTS; i++) {
+    // Unused slots keep zeroed attenuation constants, which would divide by zero
+    for (int i = 0; i < min(numLights, MAX_LIGHTS); i++) {
         result += calculatePointLight(lights[i], norm, fragPos, viewDir, objectColor);
     }
 
-    FragColor = vec4(result, 1.0);
+    FragColor = vec4(result + material_emissive + highlightColor, 1.0);
 }
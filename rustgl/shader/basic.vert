@@ -3,20 +3,26 @@ layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec3 aColor;
 layout (location = 2) in vec3 aNormal;
 layout (location = 3) in vec2 aTexCoord;
+layout (location = 4) in vec3 aTangent;
+layout (location = 5) in mat4 aInstanceModel; // Per-instance model matrix (locations 5-8)
 
 out vec3 ourColor;
 out vec3 ourNormal;
+out vec3 ourTangent;
 out vec2 ourTexCoord;
 out vec3 fragPos;
 
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
+uniform bool instanced; // Use aInstanceModel instead of the model uniform
 
 void main() {
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
+    mat4 modelMatrix = instanced ? aInstanceModel : model;
+    gl_Position = projection * view * modelMatrix * vec4(aPos, 1.0);
     ourColor = aColor;
     ourTexCoord = aTexCoord;
-    fragPos = vec3(model * vec4(aPos, 1.0));
-    ourNormal = mat3(model) * aNormal;
+    fragPos = vec3(modelMatrix * vec4(aPos, 1.0));
+    ourNormal = mat3(modelMatrix) * aNormal;
+    ourTangent = mat3(modelMatrix) * aTangent;
 }
@@ -7,14 +7,79 @@ in vec2 TexCoords;
 uniform sampler2D scene;          // Original scene
 uniform sampler2D bloomBlur;      // Blurred bright areas
 uniform float bloomStrength;      // How much bloom to add (default: 1.0)
+uniform sampler2D anamorphicBlur; // Horizontal light streaks
+uniform float anamorphicStrength; // 0.0 = no streaks
+uniform sampler2D lensDirt;       // Optional dirt/smudge texture (screen space)
+uniform bool useLensDirt;         // false = bloom is not modulated
+uniform sampler2D godRays;        // Optional HDR god rays (added before tone mapping)
+uniform bool useGodRays;
+uniform float godRayStrength;
+uniform int tonemapMode;          // 0 = Reinhard, 1 = ACES, 2 = Uncharted 2
+uniform float exposure;           // Linear exposure multiplier applied before tone mapping
+uniform float chromaticAberration; // Radial RGB split in UV units at the screen corners (0.0 = off)
+uniform float vignetteIntensity;  // Edge darkening (0.0 = off, 1.0 = black corners)
+uniform bool gammaCorrect;        // true = encode the linear result for an sRGB display
+
+#include "tonemap.glsl"
 
 void main()
 {
-    vec3 sceneColor = texture(scene, TexCoords).rgb;
-    vec3 bloomColor = texture(bloomBlur, TexCoords).rgb;
+    vec3 sceneColor;
+    vec3 bloomColor;
+    vec2 fromCenter = TexCoords - vec2(0.5);
+
+    // Chromatic aberration: red and blue are sampled pushed outward/inward from the center
+    if (chromaticAberration > 0.0) {
+        vec2 offset = fromCenter * chromaticAberration * 2.0;
+        sceneColor = vec3(texture(scene, TexCoords + offset).r,
+                          texture(scene, TexCoords).g,
+                          texture(scene, TexCoords - offset).b);
+        bloomColor = vec3(texture(bloomBlur, TexCoords + offset).r,
+                          texture(bloomBlur, TexCoords).g,
+                          texture(bloomBlur, TexCoords - offset).b);
+    } else {
+        sceneColor = texture(scene, TexCoords).rgb;
+        bloomColor = texture(bloomBlur, TexCoords).rgb;
+    }
+
+    // Anamorphic streaks are added on top of the symmetric bloom
+    if (anamorphicStrength > 0.0) {
+        bloomColor += texture(anamorphicBlur, TexCoords).rgb * anamorphicStrength;
+    }
+
+    // Lens dirt: bloom only shows up where the "lens" is smudged
+    if (useLensDirt) {
+        bloomColor *= texture(lensDirt, TexCoords).rgb;
+    }
 
-    // Additive blending with strength control
+    // Additive blending with strength control (in linear HDR space)
     vec3 result = sceneColor + bloomColor * bloomStrength;
 
+    // God rays are light too, so they go in before tone mapping
+    if (useGodRays) {
+        result += texture(godRays, TexCoords).rgb * godRayStrength;
+    }
+
+    // Tone mapping: compress HDR range [0, inf) into [0, 1)
+    result *= exposure;
+    if (tonemapMode == 1) {
+        result = tonemapACES(result);
+    } else if (tonemapMode == 2) {
+        result = tonemapUncharted2(result);
+    } else {
+        result = tonemapReinhard(result);
+    }
+
+    // Vignette: smooth falloff towards the corners (applied in display range)
+    if (vignetteIntensity > 0.0) {
+        float dist = length(fromCenter) * 1.41421356; // 0 at center, 1 at the corners
+        result *= 1.0 - vignetteIntensity * smoothstep(0.4, 1.0, dist);
+    }
+
+    // Lighting happens in linear space; displays expect sRGB-encoded values
+    if (gammaCorrect) {
+        result = pow(result, vec3(1.0 / 2.2));
+    }
+
     FragColor = vec4(result, 1.0);
 }
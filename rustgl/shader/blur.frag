@@ -6,24 +6,28 @@ in vec2 TexCoords;
 
 uniform sampler2D image;
 uniform bool horizontal;  // true = horizontal blur, false = vertical blur
+uniform float texelStride; // Distance between taps in texels (1.0 = standard Gaussian)
 
-// Gaussian blur weights (5-tap)
-float weights[5] = float[] (0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+// Gaussian blur weights (center + one side), computed on the CPU from the blur sigma
+// Must match MAX_BLUR_RADIUS in bloom_renderer.rs
+const int MAX_BLUR_RADIUS = 16;
+uniform float weights[MAX_BLUR_RADIUS + 1];
+uniform int kernelRadius; // Number of taps on each side of the center (<= MAX_BLUR_RADIUS)
 
 void main()
 {
-    vec2 tex_offset = 1.0 / textureSize(image, 0);  // Size of single texel
+    vec2 tex_offset = texelStride / textureSize(image, 0);  // Size of single tap step
     vec3 result = texture(image, TexCoords).rgb * weights[0];  // Current fragment
 
     if (horizontal) {
         // Horizontal blur (sample left and right)
-        for (int i = 1; i < 5; ++i) {
+        for (int i = 1; i <= kernelRadius; ++i) {
             result += texture(image, TexCoords + vec2(tex_offset.x * i, 0.0)).rgb * weights[i];
             result += texture(image, TexCoords - vec2(tex_offset.x * i, 0.0)).rgb * weights[i];
         }
     } else {
         // Vertical blur (sample up and down)
-        for (int i = 1; i < 5; ++i) {
+        for (int i = 1; i <= kernelRadius; ++i) {
             result += texture(image, TexCoords + vec2(0.0, tex_offset.y * i)).rgb * weights[i];
             result += texture(image, TexCoords - vec2(0.0, tex_offset.y * i)).rgb * weights[i];
         }
@@ -5,19 +5,30 @@ out vec4 FragColor;
 in vec2 TexCoords;
 
 uniform sampler2D screenTexture;
-uniform float threshold;  // Brightness threshold (default: 1.0)
+uniform float threshold;  // Brightness threshold in HDR units (values > 1.0 are valid)
+uniform float knee;       // Soft knee half-width around the threshold (0.0 = hard cutoff)
+uniform bool perChannel;  // true = threshold each RGB channel separately
+
+// Weight that fades from 0 to 1 across [threshold - knee, threshold + knee]
+float kneeWeight(float value)
+{
+    if (knee <= 0.0) {
+        return step(threshold, value);
+    }
+    return smoothstep(threshold - knee, threshold + knee, value);
+}
 
 void main()
 {
     vec3 color = texture(screenTexture, TexCoords).rgb;
 
-    // Calculate perceptual brightness (weighted RGB)
-    float brightness = dot(color, vec3(0.2126, 0.7152, 0.0722));
-
-    // Only output if above threshold
-    if (brightness > threshold) {
-        FragColor = vec4(color, 1.0);
+    if (perChannel) {
+        // Each channel blooms on its own, so saturated highlights keep their hue
+        vec3 weight = vec3(kneeWeight(color.r), kneeWeight(color.g), kneeWeight(color.b));
+        FragColor = vec4(color * weight, 1.0);
     } else {
-        FragColor = vec4(0.0, 0.0, 0.0, 1.0);  // Black
+        // Calculate perceptual brightness (weighted RGB)
+        float brightness = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        FragColor = vec4(color * kneeWeight(brightness), 1.0);
     }
 }
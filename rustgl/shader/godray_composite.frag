@@ -7,10 +7,11 @@ in vec2 TexCoords;
 uniform sampler2D scene;        // Original scene
 uniform sampler2D godRays;      // Radial blur result
 uniform float godRayStrength;   // Blending strength
+uniform bool raysOnly;          // true = composite over black instead of the scene (debug)
 
 void main()
 {
-    vec3 sceneColor = texture(scene, TexCoords).rgb;
+    vec3 sceneColor = raysOnly ? vec3(0.0) : texture(scene, TexCoords).rgb;
     vec3 godRayColor = texture(godRays, TexCoords).rgb;
 
     // Additive blending
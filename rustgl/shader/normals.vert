@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 2) in vec3 aNormal;
+
+out VS_OUT {
+    vec3 normal;
+} vs_out;
+
+uniform mat4 model;
+uniform mat4 view;
+
+void main() {
+    // Work in view space so the geometry stage only has to apply the projection
+    mat3 normalMatrix = mat3(transpose(inverse(view * model)));
+    vs_out.normal = normalize(normalMatrix * aNormal);
+    gl_Position = view * model * vec4(aPos, 1.0);
+}
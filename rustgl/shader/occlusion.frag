@@ -2,13 +2,28 @@
 
 out vec4 FragColor;
 
-uniform bool isOrb;  // true = render white (orb), false = render black (occluder)
+uniform bool isOrb;  // true = render orb color, false = render black (occluder)
+uniform vec3 orbColor; // Light color carried into the rays
+
+// Depth reuse: test the orb against the already-rendered scene depth instead of
+// re-drawing every occluder into this buffer
+uniform bool useSceneDepth;
+uniform sampler2D sceneDepth;
+uniform vec2 viewportSize;   // Size of the occlusion buffer in pixels
 
 void main()
 {
+    if (useSceneDepth) {
+        float occluderDepth = texture(sceneDepth, gl_FragCoord.xy / viewportSize).r;
+        // Small bias so the orb isn't hidden by its own depth in the scene buffer
+        if (gl_FragCoord.z > occluderDepth + 0.0005) {
+            discard;
+        }
+    }
+
     if (isOrb) {
-        // Render orb as solid white
-        FragColor = vec4(1.0, 1.0, 1.0, 1.0);
+        // Render orb in its light color so the rays inherit it
+        FragColor = vec4(orbColor, 1.0);
     } else {
         // Render occluders as black
         FragColor = vec4(0.0, 0.0, 0.0, 1.0);
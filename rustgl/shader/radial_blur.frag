@@ -11,6 +11,23 @@ uniform float decay;            // Light decay factor (0.95-0.99)
 uniform float density;          // Sample density (0.5-1.0)
 uniform float weight;           // Sample weight (0.1-0.5)
 uniform int numSamples;         // Number of samples (typically 100)
+uniform float maxRadius;        // Screen-space distance from the light where rays fade out (height units)
+uniform vec3 tint;              // Color multiplied into the rays
+uniform bool dither;            // Jitter the ray start per pixel to hide banding at low sample counts
+uniform float feather;          // Width of the fade at maxRadius and of the off-screen light fade
+
+// 4x4 ordered (Bayer) dither threshold in [0, 1)
+float bayer4x4(vec2 fragCoord)
+{
+    const float pattern[16] = float[] (
+         0.0,  8.0,  2.0, 10.0,
+        12.0,  4.0, 14.0,  6.0,
+         3.0, 11.0,  1.0,  9.0,
+        15.0,  7.0, 13.0,  5.0
+    );
+    ivec2 p = ivec2(mod(fragCoord, 4.0));
+    return pattern[p.y * 4 + p.x] / 16.0;
+}
 
 void main()
 {
@@ -23,6 +40,12 @@ void main()
     // Divide by number of samples and multiply by density
     deltaTexCoord *= 1.0 / float(numSamples) * density;
 
+    // Offset the start by a fraction of a step so neighbouring pixels sample
+    // between each other's taps, turning banding into fine noise
+    if (dither) {
+        texCoord -= deltaTexCoord * bayer4x4(gl_FragCoord.xy);
+    }
+
     // Store initial sample
     vec3 color = texture(occlusionTexture, texCoord).rgb;
 
@@ -47,7 +70,16 @@ void main()
         illuminationDecay *= decay;
     }
 
+    // Radius limit: fade rays out past maxRadius from the light (aspect-corrected)
+    vec2 texSize = vec2(textureSize(occlusionTexture, 0));
+    vec2 toLight = (TexCoords - lightScreenPos) * vec2(texSize.x / texSize.y, 1.0);
+    float radialFade = 1.0 - smoothstep(max(maxRadius - feather, 0.0), maxRadius, length(toLight));
+
+    // Edge fade: rays weaken as the light moves past the screen border
+    vec2 outside = max(-lightScreenPos, lightScreenPos - vec2(1.0));
+    float edgeFade = 1.0 - smoothstep(0.0, max(feather, 0.001), max(max(outside.x, outside.y), 0.0));
+
     // Apply exposure
-    FragColor = vec4(color * exposure, 1.0);
+    FragColor = vec4(color * tint * exposure * radialFade * edgeFade, 1.0);
 }
 
@@ -0,0 +1,38 @@
+// Tone mapping curves shared by the composite passes (pulled in with #include)
+
+vec3 tonemapReinhard(vec3 color)
+{
+    return color / (color + vec3(1.0));
+}
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 tonemapACES(vec3 color)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+// John Hable's Uncharted 2 filmic curve
+vec3 uncharted2Curve(vec3 x)
+{
+    const float A = 0.15;  // Shoulder strength
+    const float B = 0.50;  // Linear strength
+    const float C = 0.10;  // Linear angle
+    const float D = 0.20;  // Toe strength
+    const float E = 0.02;  // Toe numerator
+    const float F = 0.30;  // Toe denominator
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 tonemapUncharted2(vec3 color)
+{
+    const float exposureBias = 2.0;
+    const float whitePoint = 11.2;
+    vec3 curr = uncharted2Curve(color * exposureBias);
+    vec3 whiteScale = vec3(1.0) / uncharted2Curve(vec3(whitePoint));
+    return curr * whiteScale;
+}
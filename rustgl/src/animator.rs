@@ -0,0 +1,99 @@
+use nalgebra_glm as glm;
+
+/// Circular path around a center point in the XZ plane
+#[derive(Clone, Copy, Debug)]
+pub struct Orbit {
+    pub center: glm::Vec3,
+    pub radius: f32,
+    pub speed: f32, // radians per second
+    pub phase: f32, // starting angle in radians
+}
+
+/// Vertical sine-wave bobbing
+#[derive(Clone, Copy, Debug)]
+pub struct Bob {
+    pub amplitude: f32,
+    pub frequency: f32, // cycles per second
+}
+
+/// Describes how a scene object moves every frame
+/// Combine effects by setting several fields, e.g. a spinning object that also bobs
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Animator {
+    pub rotation_velocity: glm::Vec3, // radians per second around X, Y, Z
+    pub orbit: Option<Orbit>,
+    pub bob: Option<Bob>,
+    pub light_index: Option<usize>, // Scene light that follows the object's position
+}
+
+#[allow(dead_code)]
+impl Animator {
+    /// Rotate at a constant speed (radians per second around each axis)
+    pub fn spin(rotation_velocity: glm::Vec3) -> Self {
+        Animator {
+            rotation_velocity,
+            ..Default::default()
+        }
+    }
+
+    /// Circle around `center` at `radius`, `speed` radians per second
+    pub fn orbit(center: glm::Vec3, radius: f32, speed: f32) -> Self {
+        Animator {
+            orbit: Some(Orbit {
+                center,
+                radius,
+                speed,
+                phase: 0.0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Bob up and down by `amplitude` units, `frequency` times per second
+    pub fn bob(amplitude: f32, frequency: f32) -> Self {
+        Animator {
+            bob: Some(Bob {
+                amplitude,
+                frequency,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Move the given scene light along with the object
+    pub fn with_light(mut self, light_index: usize) -> Self {
+        self.light_index = Some(light_index);
+        self
+    }
+
+    /// Advance by `delta_time`; `time` is the total animation time in seconds
+    /// Orbits are placed absolutely from `time`; without an orbit, bobbing is applied
+    /// as the change since the previous frame so the object keeps its own rest position
+    pub fn apply(
+        &self,
+        position: &mut glm::Vec3,
+        rotation: &mut glm::Vec3,
+        delta_time: f32,
+        time: f32,
+    ) {
+        *rotation += self.rotation_velocity * delta_time;
+
+        let bob_offset = |t: f32| {
+            self.bob.map_or(0.0, |bob| {
+                bob.amplitude * (t * bob.frequency * std::f32::consts::TAU).sin()
+            })
+        };
+
+        if let Some(orbit) = self.orbit {
+            let angle = orbit.phase + time * orbit.speed;
+            *position = orbit.center
+                + glm::vec3(
+                    angle.cos() * orbit.radius,
+                    bob_offset(time),
+                    angle.sin() * orbit.radius,
+                );
+        } else {
+            position.y += bob_offset(time) - bob_offset(time - delta_time);
+        }
+    }
+}
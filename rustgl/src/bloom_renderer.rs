@@ -2,67 +2,346 @@ use crate::framebuffer::Framebuffer;
 use crate::mesh::Mesh;
 use crate::performance_monitor::PerformanceMonitor;
 use crate::shader::Shader;
+use crate::texture::Texture;
 use gl::types::*;
+use serde::{Deserialize, Serialize};
+
+/// Number of levels in the downsample/upsample mip chain (1/2 down to 1/64 resolution)
+const BLOOM_MIP_LEVELS: usize = 6;
+
+/// Number of horizontal-only blur passes for anamorphic streaks
+/// Each pass doubles the tap stride, so the streak widens exponentially
+const ANAMORPHIC_PASSES: usize = 6;
+
+/// Maximum number of Gaussian taps on each side of the center
+/// Must match MAX_BLUR_RADIUS in blur.frag
+const MAX_BLUR_RADIUS: usize = 16;
+
+/// How the bright-pass result is blurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BloomMethod {
+    /// Full-resolution separable Gaussian ping-pong blur (`blur_iterations` passes)
+    Gaussian,
+    /// Progressive half-resolution downsample followed by an upsample-and-add chain
+    MipChain,
+}
+
+/// Tone mapping operator applied in the final composite (HDR -> displayable 0..1)
+/// Discriminants match the `tonemapMode` values in bloom_composite.frag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToneMapping {
+    Reinhard = 0,
+    Aces = 1,
+    Uncharted2 = 2,
+}
 
 pub struct BloomRenderer {
     // Framebuffers
     scene_fbo: Framebuffer,
+    msaa_fbo: Option<Framebuffer>, // Scene is drawn here and resolved into scene_fbo when set
     bright_pass_fbo: Framebuffer,
     blur_fbo1: Framebuffer,
     blur_fbo2: Framebuffer,
+    mip_fbos: Vec<Framebuffer>,
+    streak_fbo1: Framebuffer,
+    streak_fbo2: Framebuffer,
     composite_fbo: Framebuffer,
 
     // Shaders
     bright_pass_shader: Shader,
     blur_shader: Shader,
+    downsample_shader: Shader,
+    upsample_shader: Shader,
     composite_shader: Shader,
     screen_shader: Shader,
 
     // Geometry
     screen_quad: Mesh,
 
+    // Optional lens dirt texture modulating the bloom in screen space
+    lens_dirt: Option<Texture>,
+
+    // Optional HDR god ray buffer added in linear space before tone mapping
+    godray_texture: Option<GLuint>,
+    godray_strength: f32,
+
     // Settings
+    method: BloomMethod,
     blur_iterations: usize,
+    blur_weights: Vec<f32>, // Normalized Gaussian weights (center + one side)
+    filter_radius: f32,     // Upsample tent filter radius in texels
+    threshold_knee: f32,    // Soft knee half-width around the bright-pass threshold
+    per_channel_threshold: bool,
+    anamorphic_strength: f32, // 0.0 = symmetric bloom only
+    tonemap: ToneMapping,
+    exposure: f32,
+    vignette: f32,             // 0.0 = off
+    gamma_correction: bool,    // Encode the composite for an sRGB display
+    chromatic_aberration: f32, // 0.0 = off
+    debug_mode: u8,            // 0 = off, 1 = bright pass, 2 = blurred bloom
+
+    // Resolution scale for the bright-pass and blur buffers (0.5 = half resolution, 1.0 = full resolution)
+    resolution_scale: f32,
 }
 
 impl BloomRenderer {
-    pub fn new(width: u32, height: u32) -> Self {
+    pub fn new(width: u32, height: u32, resolution_scale: f32) -> Self {
+        // Clamp resolution scale to reasonable values (0.25 to 1.0)
+        let scale = resolution_scale.clamp(0.25, 1.0);
+        let scaled_width = (width as f32 * scale) as u32;
+        let scaled_height = (height as f32 * scale) as u32;
+
         BloomRenderer {
-            scene_fbo: Framebuffer::new(width, height),
-            bright_pass_fbo: Framebuffer::new(width, height),
-            blur_fbo1: Framebuffer::new(width, height),
-            blur_fbo2: Framebuffer::new(width, height),
+            // HDR buffers so values above 1.0 survive until tone mapping in the composite
+            scene_fbo: Framebuffer::new_hdr_with_depth_texture(width, height),
+            msaa_fbo: None,
+            bright_pass_fbo: Framebuffer::new_hdr(scaled_width, scaled_height),
+            blur_fbo1: Framebuffer::new_hdr(scaled_width, scaled_height),
+            blur_fbo2: Framebuffer::new_hdr(scaled_width, scaled_height),
+            mip_fbos: (0..BLOOM_MIP_LEVELS)
+                .map(|level| {
+                    let (mip_width, mip_height) =
+                        Self::mip_size(scaled_width, scaled_height, level);
+                    Framebuffer::new_hdr(mip_width, mip_height)
+                })
+                .collect(),
+            streak_fbo1: Framebuffer::new_hdr(scaled_width, scaled_height),
+            streak_fbo2: Framebuffer::new_hdr(scaled_width, scaled_height),
             composite_fbo: Framebuffer::new(width, height),
 
-            bright_pass_shader: Shader::new("shader/screen.vert", "shader/bright_pass.frag"),
-            blur_shader: Shader::new("shader/screen.vert", "shader/blur.frag"),
-            composite_shader: Shader::new("shader/screen.vert", "shader/bloom_composite.frag"),
-            screen_shader: Shader::new("shader/screen.vert", "shader/screen.frag"),
+            bright_pass_shader: Shader::new_or_panic(
+                "shader/screen.vert",
+                "shader/bright_pass.frag",
+            ),
+            blur_shader: Shader::new_or_panic("shader/screen.vert", "shader/blur.frag"),
+            downsample_shader: Shader::new_or_panic(
+                "shader/screen.vert",
+                "shader/bloom_downsample.frag",
+            ),
+            upsample_shader: Shader::new_or_panic(
+                "shader/screen.vert",
+                "shader/bloom_upsample.frag",
+            ),
+            composite_shader: Shader::new_or_panic(
+                "shader/screen.vert",
+                "shader/bloom_composite.frag",
+            ),
+            screen_shader: Shader::new_or_panic("shader/screen.vert", "shader/screen.frag"),
 
             screen_quad: Mesh::screen_quad(),
 
+            lens_dirt: None,
+            godray_texture: None,
+            godray_strength: 1.0,
+
+            method: BloomMethod::MipChain,
             blur_iterations: 5,
+            blur_weights: gaussian_weights(1.75),
+            filter_radius: 1.0,
+            threshold_knee: 0.5,
+            per_channel_threshold: false,
+            anamorphic_strength: 0.0,
+            tonemap: ToneMapping::Reinhard,
+            exposure: 1.0,
+            vignette: 0.0,
+            gamma_correction: false,
+            chromatic_aberration: 0.0,
+            debug_mode: 0,
+            resolution_scale: scale,
         }
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
+        let scaled_width = (width as f32 * self.resolution_scale) as u32;
+        let scaled_height = (height as f32 * self.resolution_scale) as u32;
+
         self.scene_fbo.resize(width, height);
-        self.bright_pass_fbo.resize(width, height);
-        self.blur_fbo1.resize(width, height);
-        self.blur_fbo2.resize(width, height);
+        if let Some(msaa_fbo) = &mut self.msaa_fbo {
+            msaa_fbo.resize(width, height);
+        }
+        self.bright_pass_fbo.resize(scaled_width, scaled_height);
+        self.blur_fbo1.resize(scaled_width, scaled_height);
+        self.blur_fbo2.resize(scaled_width, scaled_height);
+        for (level, mip_fbo) in self.mip_fbos.iter_mut().enumerate() {
+            let (mip_width, mip_height) = Self::mip_size(scaled_width, scaled_height, level);
+            mip_fbo.resize(mip_width, mip_height);
+        }
+        self.streak_fbo1.resize(scaled_width, scaled_height);
+        self.streak_fbo2.resize(scaled_width, scaled_height);
         self.composite_fbo.resize(width, height);
     }
 
+    /// Every shader owned by the renderer, e.g. for hot reloading
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        vec![
+            &mut self.bright_pass_shader,
+            &mut self.blur_shader,
+            &mut self.downsample_shader,
+            &mut self.upsample_shader,
+            &mut self.composite_shader,
+            &mut self.screen_shader,
+        ]
+    }
+
+    /// Size of a mip level: level 0 is half the bright-pass resolution, each further level halves again
+    fn mip_size(width: u32, height: u32, level: usize) -> (u32, u32) {
+        (
+            (width >> (level + 1)).max(1),
+            (height >> (level + 1)).max(1),
+        )
+    }
+
     /// Get the scene texture for use by other post-processing effects
     #[allow(dead_code)]
     pub fn scene_texture(&self) -> GLuint {
         self.scene_fbo.texture()
     }
 
+    /// Get the scene depth texture (filled by the scene pass of `render`)
+    pub fn scene_depth_texture(&self) -> Option<GLuint> {
+        self.scene_fbo.depth_texture()
+    }
+
+    /// Get the final composited (bloom + tone mapped) texture
+    /// The composite always renders into an internal framebuffer, so later
+    /// post-processing effects can chain from this instead of the backbuffer
     pub fn composite_texture(&self) -> GLuint {
         self.composite_fbo.texture()
     }
 
+    /// The framebuffer behind `composite_texture`, e.g. for reading it back
+    pub fn composite_framebuffer(&self) -> &Framebuffer {
+        &self.composite_fbo
+    }
+
+    /// Set the MSAA sample count for the scene pass (0 or 1 = off)
+    /// The multisampled buffer is only recreated when the count actually changes
+    pub fn set_msaa_samples(&mut self, samples: u32) {
+        let samples = samples.min(Framebuffer::max_samples());
+        let samples = if samples > 1 { samples } else { 0 };
+        let current = self.msaa_fbo.as_ref().map_or(0, |fbo| fbo.samples());
+        if samples == current {
+            return;
+        }
+
+        self.msaa_fbo = if samples > 0 {
+            Some(Framebuffer::new_multisampled(
+                self.scene_fbo.width(),
+                self.scene_fbo.height(),
+                samples,
+            ))
+        } else {
+            None
+        };
+    }
+
+    /// Set the number of blur iterations (each iteration = 1 horizontal + 1 vertical pass)
+    /// More iterations give a wider, softer glow; fewer are faster
+    pub fn set_blur_iterations(&mut self, n: usize) {
+        self.blur_iterations = n.max(1);
+    }
+
+    /// Set the soft knee width: pixels within `knee` of the threshold fade in smoothly
+    /// instead of popping on/off (0.0 = hard cutoff)
+    pub fn set_threshold_knee(&mut self, knee: f32) {
+        self.threshold_knee = knee.max(0.0);
+    }
+
+    /// Threshold each color channel separately instead of perceptual brightness
+    pub fn set_per_channel_threshold(&mut self, per_channel: bool) {
+        self.per_channel_threshold = per_channel;
+    }
+
+    /// Set the strength of horizontal anamorphic light streaks (0.0 = disabled)
+    pub fn set_anamorphic(&mut self, strength: f32) {
+        self.anamorphic_strength = strength.max(0.0);
+    }
+
+    /// Select the tone mapping operator and exposure used by the final composite
+    pub fn set_tonemap(&mut self, mode: ToneMapping, exposure: f32) {
+        self.tonemap = mode;
+        self.exposure = exposure.max(0.0);
+    }
+
+    /// Set the strength of the edge vignette (0.0 = disabled, 1.0 = black corners)
+    pub fn set_vignette(&mut self, intensity: f32) {
+        self.vignette = intensity.clamp(0.0, 1.0);
+    }
+
+    /// Gamma-encode the final composite (linear -> sRGB), pairs with sRGB textures
+    pub fn set_gamma_correction(&mut self, enabled: bool) {
+        self.gamma_correction = enabled;
+    }
+
+    /// Set the radial red/blue channel split at the screen corners in UV units (0.0 = disabled)
+    pub fn set_chromatic_aberration(&mut self, amount: f32) {
+        self.chromatic_aberration = amount.max(0.0);
+    }
+
+    /// Show an intermediate bloom buffer instead of the composite
+    /// 0 = off (normal composite), 1 = bright pass, 2 = blurred bloom
+    pub fn set_debug_mode(&mut self, mode: u8) {
+        self.debug_mode = mode;
+    }
+
+    /// Returns true if a debug buffer is being shown instead of the composite
+    pub fn is_debug_view(&self) -> bool {
+        self.debug_mode != 0
+    }
+
+    /// Set a dirt/smudge texture that the bloom contribution is multiplied by
+    #[allow(dead_code)]
+    pub fn set_lens_dirt(&mut self, texture: Texture) {
+        self.lens_dirt = Some(texture);
+    }
+
+    /// Remove the lens dirt texture (bloom is applied unmodulated)
+    #[allow(dead_code)]
+    pub fn clear_lens_dirt(&mut self) {
+        self.lens_dirt = None;
+    }
+
+    /// Set the Gaussian sigma (in texels) of the separable blur
+    /// Larger values give a softer blur per pass; the kernel radius grows to 3 sigma
+    pub fn set_blur_sigma(&mut self, sigma: f32) {
+        self.blur_weights = gaussian_weights(sigma);
+    }
+
+    /// Upload the Gaussian kernel to the blur shader (shader must be in use)
+    fn upload_blur_weights(&self) {
+        self.blur_shader
+            .set_int("kernelRadius", self.blur_weights.len() as i32 - 1);
+        self.blur_shader
+            .set_float_array("weights", &self.blur_weights);
+    }
+
+    /// Add an HDR god ray texture to the scene in linear space (before tone mapping)
+    /// Pass None to composite without god rays
+    pub fn set_godrays(&mut self, texture: Option<GLuint>, strength: f32) {
+        self.godray_texture = texture;
+        self.godray_strength = strength;
+    }
+
+    /// Bind the god ray texture and uniforms on the composite shader (shader must be in use)
+    fn bind_godrays(&self) {
+        if let Some(godray_texture) = self.godray_texture {
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE4);
+                gl::BindTexture(gl::TEXTURE_2D, godray_texture);
+            }
+            self.composite_shader.set_int("godRays", 4);
+        }
+        self.composite_shader
+            .set_bool("useGodRays", self.godray_texture.is_some());
+        self.composite_shader
+            .set_float("godRayStrength", self.godray_strength);
+    }
+
+    /// Select the blur algorithm used to spread the bright-pass result
+    pub fn set_method(&mut self, method: BloomMethod) {
+        self.method = method;
+    }
+
     /// Main entry point - renders the scene with optional bloom
     pub fn render<F>(
         &mut self,
@@ -76,15 +355,51 @@ impl BloomRenderer {
     ) where
         F: FnOnce(),
     {
-        // Pass 1: Render scene to framebuffer
+        self.render_scene_pass(render_scene, perf_monitor);
+        self.finish(
+            threshold,
+            strength,
+            enabled,
+            window_width,
+            window_height,
+            perf_monitor,
+        );
+    }
+
+    /// Pass 1 only: render the scene into the internal HDR framebuffer
+    /// Use with `finish` when other effects need the scene (or its depth) before bloom runs
+    pub fn render_scene_pass<F>(&mut self, render_scene: F, perf_monitor: &mut PerformanceMonitor)
+    where
+        F: FnOnce(),
+    {
         perf_monitor.begin("1. Scene Render");
-        self.scene_fbo.bind();
-        render_scene();
+        if let Some(msaa_fbo) = &self.msaa_fbo {
+            msaa_fbo.bind();
+            render_scene();
+            // Later passes (and the godray depth test) read the single-sample buffer
+            msaa_fbo.resolve_to(&self.scene_fbo);
+        } else {
+            self.scene_fbo.bind();
+            render_scene();
+        }
         perf_monitor.end("1. Scene Render");
+    }
 
+    /// Passes 2+: bloom (or plain tone mapping) on the scene from `render_scene_pass`
+    pub fn finish(
+        &mut self,
+        threshold: f32,
+        strength: f32,
+        enabled: bool,
+        window_width: i32,
+        window_height: i32,
+        perf_monitor: &mut PerformanceMonitor,
+    ) {
+        let scene_texture = self.scene_fbo.texture();
         if enabled {
             // Passes 2-5: Apply bloom effect
             self.apply_bloom(
+                scene_texture,
                 threshold,
                 strength,
                 window_width,
@@ -93,13 +408,37 @@ impl BloomRenderer {
             );
         } else {
             // Just render scene without bloom
-            self.render_passthrough(window_width, window_height, perf_monitor);
+            self.render_passthrough(scene_texture, window_width, window_height, perf_monitor);
         }
     }
 
+    /// Run bloom on an externally rendered HDR scene texture (e.g. an MSAA-resolved
+    /// or deferred-lit buffer), skipping the internal scene pass.
+    /// The result ends up in `composite_texture()` just like `render`.
+    #[allow(dead_code)]
+    pub fn apply_to_texture(
+        &mut self,
+        scene_texture: GLuint,
+        threshold: f32,
+        strength: f32,
+        window_width: i32,
+        window_height: i32,
+        perf_monitor: &mut PerformanceMonitor,
+    ) {
+        self.apply_bloom(
+            scene_texture,
+            threshold,
+            strength,
+            window_width,
+            window_height,
+            perf_monitor,
+        );
+    }
+
     /// Apply the full bloom pipeline (bright pass + blur + composite)
     fn apply_bloom(
         &mut self,
+        scene_texture: GLuint,
         threshold: f32,
         strength: f32,
         window_width: i32,
@@ -116,14 +455,92 @@ impl BloomRenderer {
 
             self.bright_pass_shader.use_program();
             gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, self.scene_fbo.texture());
+            gl::BindTexture(gl::TEXTURE_2D, scene_texture);
             self.bright_pass_shader.set_int("screenTexture", 0);
             self.bright_pass_shader.set_float("threshold", threshold);
+            self.bright_pass_shader
+                .set_float("knee", self.threshold_knee);
+            self.bright_pass_shader
+                .set_bool("perChannel", self.per_channel_threshold);
             self.screen_quad.draw();
         }
         perf_monitor.end("2. Bloom Bright Pass");
 
-        // Passes 3 & 4: Ping-pong blur
+        // Pass 3: Blur the bright areas
+        let bloom_texture = match self.method {
+            BloomMethod::Gaussian => self.apply_gaussian_blur(perf_monitor),
+            BloomMethod::MipChain => self.apply_mip_chain(perf_monitor),
+        };
+
+        // Optional: horizontal streaks on top of the symmetric bloom
+        let streak_texture = if self.anamorphic_strength > 0.0 {
+            Some(self.apply_anamorphic_streaks(perf_monitor))
+        } else {
+            None
+        };
+
+        // Debug modes 1 & 2: Show the bloom buffers instead of the composite
+        if self.debug_mode != 0 {
+            let debug_texture = if self.debug_mode == 1 {
+                self.bright_pass_fbo.texture()
+            } else {
+                bloom_texture
+            };
+            self.render_debug_buffer(debug_texture, window_width, window_height);
+            return;
+        }
+
+        // Pass 4: Composite bloom with scene
+        perf_monitor.begin("4. Bloom Composite");
+        self.composite_fbo.bind();
+        unsafe {
+            gl::Viewport(0, 0, window_width, window_height);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            self.composite_shader.use_program();
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, scene_texture);
+            self.composite_shader.set_int("scene", 0);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, bloom_texture);
+            self.composite_shader.set_int("bloomBlur", 1);
+            self.composite_shader.set_float("bloomStrength", strength);
+            self.composite_shader
+                .set_int("tonemapMode", self.tonemap as i32);
+            self.composite_shader.set_float("exposure", self.exposure);
+            self.composite_shader
+                .set_float("vignetteIntensity", self.vignette);
+            self.composite_shader
+                .set_bool("gammaCorrect", self.gamma_correction);
+            self.composite_shader
+                .set_float("chromaticAberration", self.chromatic_aberration);
+            if let Some(streak_texture) = streak_texture {
+                gl::ActiveTexture(gl::TEXTURE3);
+                gl::BindTexture(gl::TEXTURE_2D, streak_texture);
+                self.composite_shader.set_int("anamorphicBlur", 3);
+            }
+            self.composite_shader.set_float(
+                "anamorphicStrength",
+                streak_texture.map_or(0.0, |_| self.anamorphic_strength),
+            );
+            if let Some(lens_dirt) = &self.lens_dirt {
+                lens_dirt.bind(2);
+                self.composite_shader.set_int("lensDirt", 2);
+            }
+            self.composite_shader
+                .set_bool("useLensDirt", self.lens_dirt.is_some());
+            self.bind_godrays();
+            self.screen_quad.draw();
+        }
+        Framebuffer::unbind();
+        perf_monitor.end("4. Bloom Composite");
+    }
+
+    /// Separable Gaussian ping-pong blur at full resolution
+    /// Returns the texture holding the blurred result
+    fn apply_gaussian_blur(&mut self, perf_monitor: &mut PerformanceMonitor) -> GLuint {
         perf_monitor.begin("3. Bloom Blur Passes");
         let mut horizontal = true;
         let mut first_iteration = true;
@@ -153,6 +570,8 @@ impl BloomRenderer {
                 gl::BindTexture(gl::TEXTURE_2D, source_texture);
                 self.blur_shader.set_int("image", 0);
                 self.blur_shader.set_bool("horizontal", horizontal);
+                self.blur_shader.set_float("texelStride", 1.0);
+                self.upload_blur_weights();
                 self.screen_quad.draw();
             }
 
@@ -164,8 +583,103 @@ impl BloomRenderer {
         Framebuffer::unbind();
         perf_monitor.end("3. Bloom Blur Passes");
 
-        // Pass 5: Composite bloom with scene
-        perf_monitor.begin("4. Bloom Composite");
+        self.blur_fbo2.texture()
+    }
+
+    /// Horizontal-only blur of the bright pass with a doubling tap stride each pass,
+    /// producing wide anamorphic light streaks. Returns the texture holding the streaks
+    fn apply_anamorphic_streaks(&mut self, perf_monitor: &mut PerformanceMonitor) -> GLuint {
+        perf_monitor.begin("3. Bloom Anamorphic Streaks");
+        let mut source_texture = self.bright_pass_fbo.texture();
+        let mut stride = 1.0;
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+
+            self.blur_shader.use_program();
+            self.blur_shader.set_int("image", 0);
+            self.blur_shader.set_bool("horizontal", true);
+            self.upload_blur_weights();
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            for pass in 0..ANAMORPHIC_PASSES {
+                let target = if pass % 2 == 0 {
+                    &self.streak_fbo1
+                } else {
+                    &self.streak_fbo2
+                };
+                target.bind();
+
+                gl::BindTexture(gl::TEXTURE_2D, source_texture);
+                self.blur_shader.set_float("texelStride", stride);
+                self.screen_quad.draw();
+
+                source_texture = target.texture();
+                stride *= 2.0;
+            }
+        }
+        Framebuffer::unbind();
+        perf_monitor.end("3. Bloom Anamorphic Streaks");
+
+        source_texture
+    }
+
+    /// Dual-filter bloom: downsample the bright pass through progressively smaller mips,
+    /// then upsample back up, additively blending each level into the next larger one.
+    /// Returns the texture holding the blurred result (the half-resolution mip)
+    fn apply_mip_chain(&mut self, perf_monitor: &mut PerformanceMonitor) -> GLuint {
+        perf_monitor.begin("3. Bloom Mip Chain");
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+
+            // Downsample: bright pass -> mip 0 -> mip 1 -> ... -> mip N-1
+            self.downsample_shader.use_program();
+            self.downsample_shader.set_int("srcTexture", 0);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            let mut source_texture = self.bright_pass_fbo.texture();
+            for mip_fbo in &self.mip_fbos {
+                mip_fbo.bind();
+                gl::BindTexture(gl::TEXTURE_2D, source_texture);
+                self.screen_quad.draw();
+                source_texture = mip_fbo.texture();
+            }
+
+            // Upsample: mip N-1 -> mip N-2 -> ... -> mip 0, adding onto what is already there
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            gl::BlendEquation(gl::FUNC_ADD);
+
+            self.upsample_shader.use_program();
+            self.upsample_shader.set_int("srcTexture", 0);
+            self.upsample_shader
+                .set_float("filterRadius", self.filter_radius);
+
+            for level in (1..self.mip_fbos.len()).rev() {
+                self.mip_fbos[level - 1].bind();
+                gl::BindTexture(gl::TEXTURE_2D, self.mip_fbos[level].texture());
+                self.screen_quad.draw();
+            }
+
+            gl::Disable(gl::BLEND);
+        }
+        Framebuffer::unbind();
+        perf_monitor.end("3. Bloom Mip Chain");
+
+        self.mip_fbos[0].texture()
+    }
+
+    /// Render scene without bloom (still tone mapped so brightness matches the bloom path)
+    fn render_passthrough(
+        &self,
+        scene_texture: GLuint,
+        window_width: i32,
+        window_height: i32,
+        perf_monitor: &mut PerformanceMonitor,
+    ) {
+        perf_monitor.begin("2. Passthrough (No Bloom)");
         self.composite_fbo.bind();
         unsafe {
             gl::Viewport(0, 0, window_width, window_height);
@@ -175,26 +689,32 @@ impl BloomRenderer {
 
             self.composite_shader.use_program();
             gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, self.scene_fbo.texture());
+            gl::BindTexture(gl::TEXTURE_2D, scene_texture);
             self.composite_shader.set_int("scene", 0);
             gl::ActiveTexture(gl::TEXTURE1);
             gl::BindTexture(gl::TEXTURE_2D, self.blur_fbo2.texture());
             self.composite_shader.set_int("bloomBlur", 1);
-            self.composite_shader.set_float("bloomStrength", strength);
+            self.composite_shader.set_float("bloomStrength", 0.0);
+            self.composite_shader
+                .set_int("tonemapMode", self.tonemap as i32);
+            self.composite_shader.set_float("exposure", self.exposure);
+            self.composite_shader
+                .set_float("vignetteIntensity", self.vignette);
+            self.composite_shader
+                .set_bool("gammaCorrect", self.gamma_correction);
+            self.composite_shader
+                .set_float("chromaticAberration", self.chromatic_aberration);
+            self.composite_shader.set_bool("useLensDirt", false);
+            self.bind_godrays();
+            self.composite_shader.set_float("anamorphicStrength", 0.0);
             self.screen_quad.draw();
         }
         Framebuffer::unbind();
-        perf_monitor.end("4. Bloom Composite");
+        perf_monitor.end("2. Passthrough (No Bloom)");
     }
 
-    /// Render scene without bloom
-    fn render_passthrough(
-        &self,
-        window_width: i32,
-        window_height: i32,
-        perf_monitor: &mut PerformanceMonitor,
-    ) {
-        perf_monitor.begin("2. Passthrough (No Bloom)");
+    /// Copy a bloom buffer into the composite framebuffer (untonemapped) for inspection
+    fn render_debug_buffer(&self, texture: GLuint, window_width: i32, window_height: i32) {
         self.composite_fbo.bind();
         unsafe {
             gl::Viewport(0, 0, window_width, window_height);
@@ -204,11 +724,27 @@ impl BloomRenderer {
 
             self.screen_shader.use_program();
             gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, self.scene_fbo.texture());
+            gl::BindTexture(gl::TEXTURE_2D, texture);
             self.screen_shader.set_int("screenTexture", 0);
             self.screen_quad.draw();
         }
         Framebuffer::unbind();
-        perf_monitor.end("2. Passthrough (No Bloom)");
     }
 }
+
+/// Compute normalized 1D Gaussian weights for a separable blur
+/// Returns the center weight followed by one side; the radius is 3 sigma, capped at MAX_BLUR_RADIUS
+fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let sigma = sigma.max(0.1);
+    let radius = ((sigma * 3.0).ceil() as usize).clamp(1, MAX_BLUR_RADIUS);
+    let mut weights: Vec<f32> = (0..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    // Side taps are used twice (left/right or up/down)
+    let sum = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    for w in &mut weights {
+        *w /= sum;
+    }
+    weights
+}
@@ -12,7 +12,20 @@ pub struct Camera {
 
     pub movement_speed: f32,
     pub mouse_sensitivity: f32,
-    pub zoom: f32,
+    pub zoom: f32, // Vertical field of view in degrees (smaller = zoomed in)
+    pub min_zoom: f32,
+    pub max_zoom: f32, // Widest field of view scrolling can reach
+
+    // Clip planes for the projection; a larger near/far ratio costs depth precision
+    pub near: f32,
+    pub far: f32,
+
+    // Smoothing: with a non-zero factor, input moves these targets and `update`
+    // eases the camera toward them instead of snapping
+    pub smoothing: f32, // Time constant in seconds (0 = instant)
+    target_position: glm::Vec3,
+    target_yaw: f32,
+    target_pitch: f32,
 }
 
 impl Camera {
@@ -28,6 +41,14 @@ impl Camera {
             movement_speed: 2.5,
             mouse_sensitivity: 0.1,
             zoom: 45.0,
+            min_zoom: 1.0,
+            max_zoom: 90.0,
+            near: 0.1,
+            far: 100.0,
+            smoothing: 0.0,
+            target_position: position,
+            target_yaw: yaw,
+            target_pitch: pitch,
         };
         camera.update_camera_vectors();
         camera
@@ -46,62 +67,110 @@ impl Camera {
         glm::look_at(&self.position, &(self.position + self.front), &self.up)
     }
 
+    /// Perspective projection using the current zoom as the vertical field of view
+    pub fn get_projection_matrix(&self, aspect_ratio: f32) -> glm::Mat4 {
+        glm::perspective(aspect_ratio, self.zoom.to_radians(), self.near, self.far)
+    }
+
+    /// The view frustum's clip planes, see `frustum_planes`
+    #[allow(dead_code)]
+    pub fn frustum_planes(&self, aspect_ratio: f32) -> [glm::Vec4; 6] {
+        frustum_planes(&(self.get_projection_matrix(aspect_ratio) * self.get_view_matrix()))
+    }
+
+    /// World-space ray from the camera through a point on screen (window coordinates,
+    /// origin top-left), for picking
+    /// Returns (origin, normalized direction)
+    pub fn screen_ray(
+        &self,
+        screen_x: f32,
+        screen_y: f32,
+        viewport_width: f32,
+        viewport_height: f32,
+        aspect_ratio: f32,
+    ) -> (glm::Vec3, glm::Vec3) {
+        // Normalized device coordinates (window Y points down, NDC Y points up)
+        let ndc_x = 2.0 * screen_x / viewport_width - 1.0;
+        let ndc_y = 1.0 - 2.0 * screen_y / viewport_height;
+
+        // Offset the view direction across the near plane by the field of view
+        let tan_half_fov = (self.zoom.to_radians() / 2.0).tan();
+        let dir = self.front
+            + self.right * (ndc_x * tan_half_fov * aspect_ratio)
+            + self.up * (ndc_y * tan_half_fov);
+        (self.position, glm::normalize(&dir))
+    }
+
     /// Processes keyboard input
+    /// Moves the target position when smoothing is on, otherwise the camera itself
     pub fn process_keyboard(&mut self, direction: CameraMovement, delta_time: f32) {
         let velocity = self.movement_speed * delta_time;
-        match direction {
-            CameraMovement::Forward => {
-                self.position += self.front * velocity;
-            }
-            CameraMovement::Backward => {
-                self.position -= self.front * velocity;
-            }
-            CameraMovement::Left => {
-                self.position -= self.right * velocity;
-            }
-            CameraMovement::Right => {
-                self.position += self.right * velocity;
-            }
-            CameraMovement::Up => {
-                self.position += self.up * velocity;
-            }
-            CameraMovement::Down => {
-                self.position -= self.up * velocity;
-            }
+        let offset = match direction {
+            CameraMovement::Forward => self.front * velocity,
+            CameraMovement::Backward => -self.front * velocity,
+            CameraMovement::Left => -self.right * velocity,
+            CameraMovement::Right => self.right * velocity,
+            CameraMovement::Up => self.up * velocity,
+            CameraMovement::Down => -self.up * velocity,
+        };
+
+        self.target_position += offset;
+        if !self.is_smoothing() {
+            self.position = self.target_position;
         }
     }
 
-    /// Processes mouse movement (for mouse look, which we'll add later)
+    /// Processes mouse movement (mouse look offsets, also used by the arrow keys)
     pub fn process_mouse_movement(&mut self, x_offset: f32, y_offset: f32, constrain_pitch: bool) {
         let x_offset = x_offset * self.mouse_sensitivity;
         let y_offset = y_offset * self.mouse_sensitivity;
 
-        self.yaw += x_offset;
-        self.pitch += y_offset;
+        self.target_yaw += x_offset;
+        self.target_pitch += y_offset;
 
         // Constrain pitch to prevent screen flip
         if constrain_pitch {
-            if self.pitch > 89.0 {
-                self.pitch = 89.0;
+            if self.target_pitch > 89.0 {
+                self.target_pitch = 89.0;
             }
-            if self.pitch < -89.0 {
-                self.pitch = -89.0;
+            if self.target_pitch < -89.0 {
+                self.target_pitch = -89.0;
             }
         }
 
-        self.update_camera_vectors();
+        if !self.is_smoothing() {
+            self.yaw = self.target_yaw;
+            self.pitch = self.target_pitch;
+            self.update_camera_vectors();
+        }
     }
 
-    /// Processes mouse scroll (for zoom, which we'll add later)
-    #[allow(dead_code)]
+    /// Processes mouse scroll: scrolling up narrows the field of view to zoom in
     pub fn process_mouse_scroll(&mut self, y_offset: f32) {
-        self.zoom -= y_offset;
-        if self.zoom < 1.0 {
-            self.zoom = 1.0;
-        }
-        if self.zoom > 45.0 {
-            self.zoom = 45.0;
+        self.zoom = (self.zoom - y_offset).clamp(self.min_zoom, self.max_zoom);
+    }
+
+    /// Advance smoothing by one frame: ease position, yaw and pitch toward their targets
+    /// Call once per frame after input; with smoothing at 0 this just keeps the targets
+    /// in sync so direct writes to `position`/`yaw`/`pitch` stick
+    pub fn update(&mut self, delta_time: f32) {
+        if !self.is_smoothing() {
+            self.target_position = self.position;
+            self.target_yaw = self.yaw;
+            self.target_pitch = self.pitch;
+            return;
         }
+
+        // Exponential decay toward the target, independent of frame rate
+        let t = 1.0 - (-delta_time / self.smoothing).exp();
+        self.position = glm::lerp(&self.position, &self.target_position, t);
+        self.yaw += (self.target_yaw - self.yaw) * t;
+        self.pitch += (self.target_pitch - self.pitch) * t;
+        self.update_camera_vectors();
+    }
+
+    fn is_smoothing(&self) -> bool {
+        self.smoothing > 0.0
     }
 
     pub fn update_camera_vectors(&mut self) {
@@ -117,6 +186,32 @@ impl Camera {
     }
 }
 
+/// Extract the six clip planes (left, right, bottom, top, near, far) from a
+/// projection * view matrix
+/// Each plane is (normal, distance) with the normal pointing into the frustum and
+/// normalized, so `dot(normal, p) + distance` is the signed distance of point p
+pub fn frustum_planes(view_projection: &glm::Mat4) -> [glm::Vec4; 6] {
+    let row = |i: usize| view_projection.row(i).transpose();
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+    [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| plane / plane.xyz().norm())
+}
+
+/// Whether an axis-aligned box (min, max corners) is at least partly inside the frustum
+/// Conservative: a box near a frustum corner can pass while being just outside
+pub fn box_in_frustum(planes: &[glm::Vec4; 6], min: &glm::Vec3, max: &glm::Vec3) -> bool {
+    planes.iter().all(|plane| {
+        // The box corner furthest along the plane normal; if even that is behind
+        // the plane, the whole box is
+        let corner = glm::vec3(
+            if plane.x >= 0.0 { max.x } else { min.x },
+            if plane.y >= 0.0 { max.y } else { min.y },
+            if plane.z >= 0.0 { max.z } else { min.z },
+        );
+        glm::dot(&plane.xyz(), &corner) + plane.w >= 0.0
+    })
+}
+
 /// Camera movement directions
 #[derive(Debug, Clone, Copy)]
 pub enum CameraMovement {
@@ -0,0 +1,181 @@
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Smallest window edge the config may ask for; the post-processing buffers are
+/// scaled down from the window size and must not end up empty
+pub const MIN_WINDOW_SIZE: u32 = 64;
+
+/// Frame limiter range offered by the UI
+pub const MIN_FRAME_LIMIT_FPS: f32 = 30.0;
+pub const MAX_FRAME_LIMIT_FPS: f32 = 500.0;
+
+/// Camera limits; the projection degenerates at a zero near plane or a 180° FOV
+pub const MIN_CAMERA_NEAR: f32 = 0.001;
+pub const MIN_CAMERA_FAR_GAP: f32 = 0.1;
+pub const MIN_CAMERA_FOV: f32 = 1.0;
+pub const MAX_CAMERA_FOV: f32 = 170.0;
+
+/// MSAA sample counts offered by the UI (0 = off)
+pub const SUPPORTED_MSAA_SAMPLES: [u32; 4] = [0, 2, 4, 8];
+
+/// Initial window size in screen coordinates
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 1024,
+            height: 768,
+        }
+    }
+}
+
+/// Requested OpenGL context version (core profile)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlConfig {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Default for GlConfig {
+    fn default() -> Self {
+        // macOS is limited to OpenGL 4.1
+        if cfg!(target_os = "macos") {
+            GlConfig { major: 4, minor: 1 }
+        } else {
+            GlConfig { major: 4, minor: 5 }
+        }
+    }
+}
+
+/// Startup settings read from a TOML file
+/// Missing sections or keys fall back to their defaults, so a partial file is fine
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub gl: GlConfig,
+    pub settings: AppState,
+}
+
+impl Config {
+    /// Load the config file, or the defaults if it doesn't exist or can't be parsed
+    pub fn load(path: &str) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Config::default(),
+        };
+
+        match toml::from_str(&contents) {
+            Ok(mut config) => {
+                println!("Loaded settings from {}", path);
+                Self::sanitize(&mut config);
+                config
+            }
+            Err(e) => {
+                eprintln!("Failed to parse {}, using defaults: {}", path, e);
+                Config::default()
+            }
+        }
+    }
+
+    /// Pull values that parse fine but can't be used back into range, logging each fix
+    fn sanitize(&mut self) {
+        if self.window.width < MIN_WINDOW_SIZE || self.window.height < MIN_WINDOW_SIZE {
+            let fixed = (
+                self.window.width.max(MIN_WINDOW_SIZE),
+                self.window.height.max(MIN_WINDOW_SIZE),
+            );
+            eprintln!(
+                "Config: window size {}x{} is too small, using {}x{}",
+                self.window.width, self.window.height, fixed.0, fixed.1
+            );
+            (self.window.width, self.window.height) = fixed;
+        }
+
+        // Same range as the UI slider; 0 would make the target frame time infinite
+        let fps = self.settings.frame_limit_fps;
+        let fixed_fps = if fps.is_finite() {
+            fps.clamp(MIN_FRAME_LIMIT_FPS, MAX_FRAME_LIMIT_FPS)
+        } else {
+            crate::TARGET_FPS
+        };
+        if fixed_fps != fps {
+            eprintln!(
+                "Config: frame_limit_fps {} is out of range, using {}",
+                fps, fixed_fps
+            );
+            self.settings.frame_limit_fps = fixed_fps;
+        }
+
+        // Round down to the nearest sample count the UI offers
+        let samples = self.settings.msaa_samples;
+        let fixed_samples = SUPPORTED_MSAA_SAMPLES
+            .iter()
+            .copied()
+            .filter(|&supported| supported <= samples)
+            .max()
+            .unwrap_or(0);
+        if fixed_samples != samples {
+            eprintln!(
+                "Config: msaa_samples {} isn't supported, using {}",
+                samples, fixed_samples
+            );
+            self.settings.msaa_samples = fixed_samples;
+        }
+
+        let near = self.settings.camera_near;
+        let fixed_near = if near.is_finite() {
+            near.max(MIN_CAMERA_NEAR)
+        } else {
+            0.1
+        };
+        if fixed_near != near {
+            eprintln!(
+                "Config: camera_near {} is invalid, using {}",
+                near, fixed_near
+            );
+            self.settings.camera_near = fixed_near;
+        }
+
+        // Far must stay beyond near, otherwise the depth range is empty or inverted
+        let far = self.settings.camera_far;
+        let fixed_far = if far.is_finite() {
+            far.max(fixed_near + MIN_CAMERA_FAR_GAP)
+        } else {
+            100.0
+        };
+        if fixed_far != far {
+            eprintln!("Config: camera_far {} is invalid, using {}", far, fixed_far);
+            self.settings.camera_far = fixed_far;
+        }
+
+        let fov = self.settings.camera_max_fov;
+        let fixed_fov = if fov.is_finite() {
+            fov.clamp(MIN_CAMERA_FOV, MAX_CAMERA_FOV)
+        } else {
+            90.0
+        };
+        if fixed_fov != fov {
+            eprintln!(
+                "Config: camera_max_fov {} is out of range, using {}",
+                fov, fixed_fov
+            );
+            self.settings.camera_max_fov = fixed_fov;
+        }
+    }
+
+    /// Write the config file (overwrites any existing one)
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
@@ -1,18 +1,54 @@
+use crate::gpu_memory::{self, GpuResource};
 use gl::types::*;
 
 pub struct Framebuffer {
     fbo: GLuint,
     color_texture: GLuint,
-    rbo: GLuint,
+    rbo: GLuint, // Depth/stencil renderbuffer (0 when a depth texture is used)
+    depth_texture: Option<GLuint>, // Sampleable depth/stencil texture
     width: u32,
     height: u32,
+    // Color attachment format (kept so resize reallocates with the same format)
+    internal_format: GLenum,
+    data_type: GLenum,
+    samples: u32, // 0 = regular texture, otherwise a multisample texture and renderbuffer
 }
 
 impl Framebuffer {
+    /// Create a standard 8-bit per channel framebuffer (values clamped to 0..1)
     pub fn new(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::RGB8, gl::UNSIGNED_BYTE, false)
+    }
+
+    /// Create a standard framebuffer whose depth is stored in a sampleable texture
+    #[allow(dead_code)]
+    pub fn new_with_depth_texture(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::RGB8, gl::UNSIGNED_BYTE, true)
+    }
+
+    /// Create a 16-bit floating point framebuffer so colors above 1.0 are preserved
+    /// Uses RGBA16F rather than RGB16F since only the former is guaranteed color-renderable
+    pub fn new_hdr(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::RGBA16F, gl::FLOAT, false)
+    }
+
+    /// Create an HDR framebuffer whose depth is stored in a texture that can be sampled
+    /// by later passes (instead of a write-only renderbuffer)
+    pub fn new_hdr_with_depth_texture(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::RGBA16F, gl::FLOAT, true)
+    }
+
+    fn with_format(
+        width: u32,
+        height: u32,
+        internal_format: GLenum,
+        data_type: GLenum,
+        use_depth_texture: bool,
+    ) -> Self {
         let mut fbo = 0;
         let mut color_texture = 0;
         let mut rbo = 0;
+        let mut depth_texture = None;
 
         unsafe {
             gl::GenFramebuffers(1, &mut fbo);
@@ -23,16 +59,19 @@ impl Framebuffer {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RGB as i32,
+                internal_format as i32,
                 width as i32,
                 height as i32,
                 0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
+                Self::pixel_format(internal_format),
+                data_type,
                 std::ptr::null(),
             );
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            // Clamp so screen-space filters don't pull in texels from the opposite edge
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
 
             gl::FramebufferTexture2D(
                 gl::FRAMEBUFFER,
@@ -42,16 +81,110 @@ impl Framebuffer {
                 0,
             );
 
-            // Create depth renderbuffer
+            if use_depth_texture {
+                // Create sampleable depth texture
+                let mut texture = 0;
+                gl::GenTextures(1, &mut texture);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                Self::allocate_depth_texture(width, height);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_STENCIL_ATTACHMENT,
+                    gl::TEXTURE_2D,
+                    texture,
+                    0,
+                );
+                depth_texture = Some(texture);
+            } else {
+                // Create depth renderbuffer
+                gl::GenRenderbuffers(1, &mut rbo);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, rbo);
+                gl::RenderbufferStorage(
+                    gl::RENDERBUFFER,
+                    gl::DEPTH24_STENCIL8,
+                    width as i32,
+                    height as i32,
+                );
+
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_STENCIL_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    rbo,
+                );
+            }
+
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                panic!("Framebuffer is not complete!");
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        gpu_memory::track_alloc(
+            GpuResource::Framebuffer,
+            Self::byte_size(width, height, internal_format),
+        );
+
+        Framebuffer {
+            fbo,
+            color_texture,
+            rbo,
+            depth_texture,
+            width,
+            height,
+            internal_format,
+            data_type,
+            samples: 0,
+        }
+    }
+
+    /// Create a multisampled HDR framebuffer for anti-aliased rendering
+    /// Its color can't be sampled directly; `resolve_to` a regular framebuffer first.
+    /// `samples` is clamped to what the driver supports
+    pub fn new_multisampled(width: u32, height: u32, samples: u32) -> Self {
+        let samples = samples.clamp(1, Self::max_samples());
+        let internal_format = gl::RGBA16F;
+        let mut fbo = 0;
+        let mut color_texture = 0;
+        let mut rbo = 0;
+
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+
+            gl::GenTextures(1, &mut color_texture);
+            gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, color_texture);
+            gl::TexImage2DMultisample(
+                gl::TEXTURE_2D_MULTISAMPLE,
+                samples as i32,
+                internal_format,
+                width as i32,
+                height as i32,
+                gl::TRUE,
+            );
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D_MULTISAMPLE,
+                color_texture,
+                0,
+            );
+
             gl::GenRenderbuffers(1, &mut rbo);
             gl::BindRenderbuffer(gl::RENDERBUFFER, rbo);
-            gl::RenderbufferStorage(
+            gl::RenderbufferStorageMultisample(
                 gl::RENDERBUFFER,
+                samples as i32,
                 gl::DEPTH24_STENCIL8,
                 width as i32,
                 height as i32,
             );
-
             gl::FramebufferRenderbuffer(
                 gl::FRAMEBUFFER,
                 gl::DEPTH_STENCIL_ATTACHMENT,
@@ -60,18 +193,61 @@ impl Framebuffer {
             );
 
             if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                panic!("Framebuffer is not complete!");
+                panic!("Multisampled framebuffer is not complete!");
             }
 
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
         }
 
-        Framebuffer {
+        let framebuffer = Framebuffer {
             fbo,
             color_texture,
             rbo,
+            depth_texture: None,
             width,
             height,
+            internal_format,
+            data_type: gl::FLOAT,
+            samples,
+        };
+        gpu_memory::track_alloc(GpuResource::Framebuffer, framebuffer.gpu_bytes());
+        framebuffer
+    }
+
+    /// Largest sample count the driver supports for multisampled framebuffers
+    pub fn max_samples() -> u32 {
+        let mut max_samples = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+        }
+        max_samples.max(1) as u32
+    }
+
+    /// Number of samples per pixel (0 for a regular framebuffer)
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Copy (and for multisampled framebuffers, average) color and depth into `target`
+    /// Both framebuffers must be the same size, since multisample resolves can't scale
+    pub fn resolve_to(&self, target: &Framebuffer) {
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target.fbo);
+            // NEAREST is required when depth/stencil are included
+            gl::BlitFramebuffer(
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                0,
+                0,
+                target.width as i32,
+                target.height as i32,
+                gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
         }
     }
 
@@ -90,15 +266,136 @@ impl Framebuffer {
         }
     }
 
+    /// Whether the default framebuffer (the window) stores sRGB-encoded color,
+    /// i.e. whether enabling `GL_FRAMEBUFFER_SRGB` encodes what is drawn to the screen
+    pub fn default_is_srgb() -> bool {
+        let mut object_type = 0;
+        let mut encoding = 0;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            // Querying the encoding of a missing buffer (e.g. a surfaceless context) is an error
+            gl::GetFramebufferAttachmentParameteriv(
+                gl::FRAMEBUFFER,
+                gl::BACK_LEFT,
+                gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE,
+                &mut object_type,
+            );
+            if object_type as GLenum == gl::NONE {
+                return false;
+            }
+            gl::GetFramebufferAttachmentParameteriv(
+                gl::FRAMEBUFFER,
+                gl::BACK_LEFT,
+                gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+                &mut encoding,
+            );
+        }
+        encoding as GLenum == gl::SRGB
+    }
+
     /// Get the color texture ID for rendering to screen
+    /// (a `TEXTURE_2D_MULTISAMPLE` for multisampled framebuffers)
     pub fn texture(&self) -> GLuint {
         self.color_texture
     }
 
+    /// Read the color attachment back as tightly packed RGBA8 rows, bottom row first
+    /// HDR values are clamped to 0..1. Multisampled framebuffers must be resolved first
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let mut pixels = vec![0u8; self.width as usize * self.height as usize * 4];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::ReadPixels(
+                0,
+                0,
+                self.width as GLsizei,
+                self.height as GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+        pixels
+    }
+
+    /// Get the depth texture ID, if this framebuffer was created with one
+    pub fn depth_texture(&self) -> Option<GLuint> {
+        self.depth_texture
+    }
+
+    /// Allocate DEPTH24_STENCIL8 storage for the currently bound depth texture
+    unsafe fn allocate_depth_texture(width: u32, height: u32) {
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::DEPTH24_STENCIL8 as i32,
+            width as i32,
+            height as i32,
+            0,
+            gl::DEPTH_STENCIL,
+            gl::UNSIGNED_INT_24_8,
+            std::ptr::null(),
+        );
+    }
+
+    /// Approximate GPU memory used by the color and depth attachments
+    pub fn gpu_bytes(&self) -> usize {
+        Self::byte_size(self.width, self.height, self.internal_format)
+            * self.samples.max(1) as usize
+    }
+
+    /// Client pixel format matching the color attachment's channel count
+    fn pixel_format(internal_format: GLenum) -> GLenum {
+        match internal_format {
+            gl::RGBA8 | gl::RGBA16F | gl::RGBA32F => gl::RGBA,
+            _ => gl::RGB,
+        }
+    }
+
+    fn byte_size(width: u32, height: u32, internal_format: GLenum) -> usize {
+        let color_bytes_per_pixel = match internal_format {
+            gl::RGB16F => 6,
+            gl::RGBA16F => 8,
+            gl::RGB32F => 12,
+            gl::RGBA32F => 16,
+            gl::RGBA8 => 4,
+            _ => 3,
+        };
+        // Depth/stencil is DEPTH24_STENCIL8 (4 bytes) for both the texture and renderbuffer
+        width as usize * height as usize * (color_bytes_per_pixel + 4)
+    }
+
     /// Resize the framebuffer (useful for window resizing)
     pub fn resize(&mut self, width: u32, height: u32) {
+        gpu_memory::track_free(GpuResource::Framebuffer, self.gpu_bytes());
         self.width = width;
         self.height = height;
+        gpu_memory::track_alloc(GpuResource::Framebuffer, self.gpu_bytes());
+
+        if self.samples > 0 {
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, self.color_texture);
+                gl::TexImage2DMultisample(
+                    gl::TEXTURE_2D_MULTISAMPLE,
+                    self.samples as i32,
+                    self.internal_format,
+                    width as i32,
+                    height as i32,
+                    gl::TRUE,
+                );
+                gl::BindRenderbuffer(gl::RENDERBUFFER, self.rbo);
+                gl::RenderbufferStorageMultisample(
+                    gl::RENDERBUFFER,
+                    self.samples as i32,
+                    gl::DEPTH24_STENCIL8,
+                    width as i32,
+                    height as i32,
+                );
+            }
+            return;
+        }
 
         unsafe {
             // Resize color texture
@@ -106,23 +403,29 @@ impl Framebuffer {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RGB as i32,
+                self.internal_format as i32,
                 width as i32,
                 height as i32,
                 0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
+                Self::pixel_format(self.internal_format),
+                self.data_type,
                 std::ptr::null(),
             );
 
-            // Resize renderbuffer
-            gl::BindRenderbuffer(gl::RENDERBUFFER, self.rbo);
-            gl::RenderbufferStorage(
-                gl::RENDERBUFFER,
-                gl::DEPTH24_STENCIL8,
-                width as i32,
-                height as i32,
-            );
+            if let Some(depth_texture) = self.depth_texture {
+                // Resize depth texture
+                gl::BindTexture(gl::TEXTURE_2D, depth_texture);
+                Self::allocate_depth_texture(width, height);
+            } else {
+                // Resize renderbuffer
+                gl::BindRenderbuffer(gl::RENDERBUFFER, self.rbo);
+                gl::RenderbufferStorage(
+                    gl::RENDERBUFFER,
+                    gl::DEPTH24_STENCIL8,
+                    width as i32,
+                    height as i32,
+                );
+            }
         }
     }
 
@@ -144,10 +447,15 @@ impl Framebuffer {
 
 impl Drop for Framebuffer {
     fn drop(&mut self) {
+        gpu_memory::track_free(GpuResource::Framebuffer, self.gpu_bytes());
         unsafe {
             gl::DeleteFramebuffers(1, &self.fbo);
             gl::DeleteTextures(1, &self.color_texture);
-            gl::DeleteRenderbuffers(1, &self.rbo);
+            if let Some(depth_texture) = self.depth_texture {
+                gl::DeleteTextures(1, &depth_texture);
+            } else {
+                gl::DeleteRenderbuffers(1, &self.rbo);
+            }
         }
     }
 }
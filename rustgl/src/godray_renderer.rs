@@ -1,6 +1,7 @@
 use crate::framebuffer::Framebuffer;
 use crate::mesh::Mesh;
 use crate::performance_monitor::PerformanceMonitor;
+use crate::scene::ObjectHandle;
 use crate::shader::Shader;
 use gl::types::*;
 use nalgebra_glm as glm;
@@ -21,27 +22,51 @@ pub struct GodRayRenderer {
     pub density: f32,
     pub weight: f32,
     pub num_samples: i32,
+    pub dither: bool, // Per-pixel sample jitter to hide banding at low sample counts
+    max_radius: f32,  // Screen-space distance (in screen heights) past which rays fade out
+    feather: f32,     // Width of the radius and screen-edge fades
+    tint: glm::Vec3,  // Multiplied into the rays by the radial blur
+    scene_depth: Option<GLuint>, // Reuse this depth texture for occlusion instead of re-drawing occluders
 
     // Resolution scale for performance optimization (0.5 = half resolution, 1.0 = full resolution)
     resolution_scale: f32,
+    // Full (unscaled) output size, kept so the scale can change at runtime
+    width: u32,
+    height: u32,
+    hdr: bool,
 }
 
 #[allow(dead_code)]
 impl GodRayRenderer {
-    pub fn new(width: u32, height: u32, resolution_scale: f32) -> Self {
+    /// `hdr` selects 16-bit float occlusion/blur buffers so bright rays aren't clamped at 1.0
+    pub fn new(width: u32, height: u32, resolution_scale: f32, hdr: bool) -> Self {
         // Clamp resolution scale to reasonable values (0.25 to 1.0)
         let scale = resolution_scale.clamp(0.25, 1.0);
         let scaled_width = (width as f32 * scale) as u32;
         let scaled_height = (height as f32 * scale) as u32;
+        let create_fbo = if hdr {
+            Framebuffer::new_hdr
+        } else {
+            Framebuffer::new
+        };
 
         GodRayRenderer {
-            occlusion_fbo: Framebuffer::new(scaled_width, scaled_height),
-            radial_blur_fbo: Framebuffer::new(scaled_width, scaled_height),
-
-            occlusion_shader: Shader::new("shader/occlusion.vert", "shader/occlusion.frag"),
-            radial_blur_shader: Shader::new("shader/screen.vert", "shader/radial_blur.frag"),
-            composite_shader: Shader::new("shader/screen.vert", "shader/godray_composite.frag"),
-            screen_shader: Shader::new("shader/screen.vert", "shader/screen.frag"),
+            occlusion_fbo: create_fbo(scaled_width, scaled_height),
+            radial_blur_fbo: create_fbo(scaled_width, scaled_height),
+
+            occlusion_shader: Shader::new_or_panic(
+                "shader/occlusion.vert",
+                "shader/occlusion.frag",
+            ),
+            radial_blur_shader: Shader::new_or_panic(
+                "shader/screen.vert",
+                "shader/radial_blur.frag",
+            ),
+            composite_shader: Shader::new_or_panic(
+                "shader/screen.vert",
+                "shader/godray_composite.frag",
+            ),
+            screen_shader: Shader::new_or_panic("shader/screen.vert", "shader/screen.frag"),
 
             screen_quad: Mesh::screen_quad(),
 
@@ -50,11 +75,21 @@ impl GodRayRenderer {
             density: 0.8,
             weight: 0.3,
             num_samples: 100,
+            dither: true,
+            max_radius: 2.0,
+            feather: 0.5,
+            tint: glm::vec3(1.0, 1.0, 1.0),
+            scene_depth: None,
             resolution_scale: scale,
+            width,
+            height,
+            hdr,
         }
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
         let scaled_width = (width as f32 * self.resolution_scale) as u32;
         let scaled_height = (height as f32 * self.resolution_scale) as u32;
 
@@ -62,54 +97,164 @@ impl GodRayRenderer {
         self.radial_blur_fbo.resize(scaled_width, scaled_height);
     }
 
+    /// Every shader owned by the renderer, e.g. for hot reloading
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        vec![
+            &mut self.occlusion_shader,
+            &mut self.radial_blur_shader,
+            &mut self.composite_shader,
+            &mut self.screen_shader,
+        ]
+    }
+
+    /// Change the occlusion/blur resolution scale (clamped to 0.25..1.0) and reallocate the buffers
+    pub fn set_resolution_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(0.25, 1.0);
+        if scale == self.resolution_scale {
+            return;
+        }
+        self.resolution_scale = scale;
+        self.resize(self.width, self.height);
+    }
+
+    pub fn resolution_scale(&self) -> f32 {
+        self.resolution_scale
+    }
+
+    /// Limit how far (in screen heights) rays reach from the light before fading out
+    pub fn set_max_radius(&mut self, radius: f32) {
+        self.max_radius = radius.max(0.0);
+    }
+
+    /// Set the feathering width used for the radius falloff and the fade as the light leaves the screen
+    pub fn set_feather(&mut self, feather: f32) {
+        self.feather = feather.max(0.0);
+    }
+
+    /// True if the ray buffers are floating point and should be added before tone mapping
+    pub fn is_hdr(&self) -> bool {
+        self.hdr
+    }
+
+    /// Set a color multiplied into the rays (on top of each orb's own diffuse color)
+    pub fn set_tint(&mut self, tint: glm::Vec3) {
+        self.tint = tint;
+    }
+
+    /// Build the occlusion mask from an already-rendered scene depth texture
+    /// (only the orbs are drawn and depth-tested against it), or pass None to
+    /// re-render every scene object as an occluder
+    pub fn set_scene_depth(&mut self, depth_texture: Option<GLuint>) {
+        self.scene_depth = depth_texture;
+    }
+
+    /// Render god rays from every source orb and composite them over the scene
     pub fn apply(
         &mut self,
         scene_texture: GLuint,
         scene: &crate::scene::Scene,
-        orb_index: usize,
-        light_world_pos: glm::Vec3,
+        sources: &[ObjectHandle],
         view: &glm::Mat4,
         projection: &glm::Mat4,
         strength: f32,
-        debug_mode: u8, // 0 = off, 1 = occlusion, 2 = radial blur
+        debug_mode: u8, // 0 = off, 1 = occlusion, 2 = radial blur, 3 = rays only
         window_width: i32,
         window_height: i32,
         perf_monitor: &mut PerformanceMonitor,
     ) {
-        let (light_screen_pos, is_on_screen) =
-            self.world_to_screen_checked(light_world_pos, view, projection);
+        self.render_rays(scene, sources, view, projection, perf_monitor);
+        self.present(
+            scene_texture,
+            strength,
+            debug_mode,
+            window_width,
+            window_height,
+            perf_monitor,
+        );
+    }
 
-        self.generate_occlusion_mask(scene, orb_index, view, projection, perf_monitor);
+    /// Build the occlusion masks and radial blurs without compositing
+    /// `sources` are the scene objects drawn as glowing orbs (usually the ones tagged
+    /// `GodraySource`), each casting rays from its own world position. Every source gets
+    /// its own occlusion mask holding only its orb, blurred from that orb's screen position
+    /// and added into the ray buffer, so one light never streaks another light's orb.
+    /// Returns the ray texture (e.g. for adding in linear space before tone mapping)
+    pub fn render_rays(
+        &mut self,
+        scene: &crate::scene::Scene,
+        sources: &[ObjectHandle],
+        view: &glm::Mat4,
+        projection: &glm::Mat4,
+        perf_monitor: &mut PerformanceMonitor,
+    ) -> GLuint {
+        let _scope = perf_monitor.scope("5. Godray Occlusion + Blur");
 
-        // Debug mode 1: Show occlusion buffer
-        if debug_mode == 1 {
-            self.render_debug_buffer(self.occlusion_fbo.texture(), window_width, window_height);
-            return;
+        // Start from an empty ray buffer; each source adds its rays on top
+        self.radial_blur_fbo.bind();
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
         }
 
-        // Only apply radial blur if light is reasonably close to screen
-        // (we allow some margin for off-screen rays)
-        if is_on_screen {
-            self.apply_radial_blur(light_screen_pos, perf_monitor);
-        } else {
-            // Clear the radial blur buffer if light is too far off-screen
-            self.radial_blur_fbo.bind();
+        let mut any_visible = false;
+        for &orb_index in sources {
+            // Skip the occlusion geometry pass and the blur entirely when the light is
+            // behind the camera or too far off-screen to contribute any rays
+            // (we allow some margin for off-screen rays)
+            let (light_screen_pos, is_on_screen) =
+                self.world_to_screen_checked(scene.world_position(orb_index), view, projection);
+            if !is_on_screen {
+                continue;
+            }
+            any_visible = true;
+
+            self.generate_occlusion_mask(scene, orb_index, view, projection);
+            self.apply_radial_blur(light_screen_pos);
+        }
+
+        // Keep the occlusion debug view (which shows the last source's mask) from
+        // showing a stale mask
+        if !any_visible {
+            self.occlusion_fbo.bind();
             unsafe {
                 gl::ClearColor(0.0, 0.0, 0.0, 1.0);
                 gl::Clear(gl::COLOR_BUFFER_BIT);
             }
         }
+        Framebuffer::unbind();
 
-        // Debug mode 2 & 3: Show radial blur buffer (god rays only)
+        self.radial_blur_fbo.texture()
+    }
+
+    /// Draw the result of `render_rays` to the screen: composited over the scene,
+    /// or one of the debug buffers
+    pub fn present(
+        &self,
+        scene_texture: GLuint,
+        strength: f32,
+        debug_mode: u8, // 0 = off, 1 = occlusion, 2 = radial blur, 3 = rays only
+        window_width: i32,
+        window_height: i32,
+        perf_monitor: &mut PerformanceMonitor,
+    ) {
+        // Debug mode 1: Show occlusion buffer
+        if debug_mode == 1 {
+            self.render_debug_buffer(self.occlusion_fbo.texture(), window_width, window_height);
+            return;
+        }
+
+        // Debug mode 2: Show raw radial blur buffer
         if debug_mode == 2 {
             self.render_debug_buffer(self.radial_blur_fbo.texture(), window_width, window_height);
             return;
         }
 
         // Normal mode (0): Composite with scene
+        // Debug mode 3: Same composite over black to judge ray color and intensity
         self.composite(
             scene_texture,
             strength,
+            debug_mode == 3,
             window_width,
             window_height,
             perf_monitor,
@@ -154,18 +299,16 @@ impl GodRayRenderer {
         (screen_pos, is_on_screen)
     }
 
+    /// Draw the occlusion mask for one source: its orb glowing, everything else black
     fn generate_occlusion_mask(
         &mut self,
         scene: &crate::scene::Scene,
-        orb_index: usize,
+        orb_index: ObjectHandle,
         view: &glm::Mat4,
         projection: &glm::Mat4,
-        perf_monitor: &mut PerformanceMonitor,
     ) {
-        perf_monitor.begin("5. Godray Occlusion");
         self.occlusion_fbo.bind();
         unsafe {
-            gl::Enable(gl::DEPTH_TEST);
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 
@@ -173,35 +316,65 @@ impl GodRayRenderer {
             self.occlusion_shader.set_mat4("view", view);
             self.occlusion_shader.set_mat4("projection", projection);
 
-            // Render all scene objects to build depth buffer
-            for (i, obj) in scene.objects_iter().enumerate() {
-                self.occlusion_shader
-                    .set_mat4("model", &obj.transform.to_matrix());
-
-                // Set uniform to indicate if this is the orb or an occluder
-                let is_orb = i == orb_index;
-                self.occlusion_shader.set_bool("isOrb", is_orb);
-
-                obj.mesh.draw();
+            if let Some(depth_texture) = self.scene_depth {
+                // Occluders are already in the scene depth - only draw the orb,
+                // discarding fragments hidden behind scene geometry
+                gl::Disable(gl::DEPTH_TEST);
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, depth_texture);
+                self.occlusion_shader.set_int("sceneDepth", 0);
+                self.occlusion_shader.set_bool("useSceneDepth", true);
+                self.occlusion_shader.set_vec2(
+                    "viewportSize",
+                    &glm::vec2(
+                        self.occlusion_fbo.width() as f32,
+                        self.occlusion_fbo.height() as f32,
+                    ),
+                );
+                self.occlusion_shader.set_bool("isOrb", true);
+
+                if let Some(orb) = scene.get_object(orb_index) {
+                    self.occlusion_shader
+                        .set_mat4("model", &scene.world_matrix(orb_index));
+                    self.occlusion_shader
+                        .set_vec3("orbColor", &orb.material.glow_color());
+                    orb.mesh.draw();
+                }
+            } else {
+                gl::Enable(gl::DEPTH_TEST);
+                self.occlusion_shader.set_bool("useSceneDepth", false);
+
+                // Render all scene objects to build depth buffer
+                for (index, obj) in scene.objects_iter().enumerate() {
+                    self.occlusion_shader
+                        .set_mat4("model", &scene.world_matrix(index));
+
+                    // Set uniform to indicate if this is the orb or an occluder
+                    // (other sources' orbs occlude like any other object here)
+                    let is_orb = index == orb_index;
+                    self.occlusion_shader.set_bool("isOrb", is_orb);
+                    if is_orb {
+                        self.occlusion_shader
+                            .set_vec3("orbColor", &obj.material.glow_color());
+                    }
+
+                    obj.mesh.draw();
+                }
             }
         }
 
         // Unbind the framebuffer so we can read from its texture
         Framebuffer::unbind();
-        perf_monitor.end("5. Godray Occlusion");
     }
 
-    fn apply_radial_blur(
-        &mut self,
-        light_screen_pos: glm::Vec2,
-        perf_monitor: &mut PerformanceMonitor,
-    ) {
-        perf_monitor.begin("6. Godray Radial Blur");
+    /// Radial blur of the current occlusion mask towards the light,
+    /// added on top of whatever is already in the radial blur buffer
+    fn apply_radial_blur(&mut self, light_screen_pos: glm::Vec2) {
         self.radial_blur_fbo.bind();
         unsafe {
             gl::Disable(gl::DEPTH_TEST);
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::ONE, gl::ONE);
 
             self.radial_blur_shader.use_program();
             gl::ActiveTexture(gl::TEXTURE0);
@@ -215,20 +388,26 @@ impl GodRayRenderer {
             self.radial_blur_shader.set_float("weight", self.weight);
             self.radial_blur_shader
                 .set_int("numSamples", self.num_samples);
+            self.radial_blur_shader
+                .set_float("maxRadius", self.max_radius);
+            self.radial_blur_shader.set_float("feather", self.feather);
+            self.radial_blur_shader.set_bool("dither", self.dither);
+            self.radial_blur_shader.set_vec3("tint", &self.tint);
             self.screen_quad.draw();
+            gl::Disable(gl::BLEND);
         }
-        perf_monitor.end("6. Godray Radial Blur");
     }
 
     fn composite(
         &self,
         scene_texture: GLuint,
         strength: f32,
+        rays_only: bool,
         window_width: i32,
         window_height: i32,
         perf_monitor: &mut PerformanceMonitor,
     ) {
-        perf_monitor.begin("7. Godray Composite");
+        let _scope = perf_monitor.scope("6. Godray Composite");
         Framebuffer::unbind();
         unsafe {
             gl::Disable(gl::DEPTH_TEST);
@@ -244,12 +423,13 @@ impl GodRayRenderer {
             gl::BindTexture(gl::TEXTURE_2D, self.radial_blur_fbo.texture());
             self.composite_shader.set_int("godRays", 1);
             self.composite_shader.set_float("godRayStrength", strength);
+            self.composite_shader.set_bool("raysOnly", rays_only);
             self.screen_quad.draw();
         }
-        perf_monitor.end("7. Godray Composite");
     }
 
-    fn render_passthrough(&self, scene_texture: GLuint, window_width: i32, window_height: i32) {
+    /// Draw the scene texture to the screen without god rays
+    pub fn render_passthrough(&self, scene_texture: GLuint, window_width: i32, window_height: i32) {
         Framebuffer::unbind();
         unsafe {
             gl::Viewport(0, 0, window_width, window_height);
@@ -0,0 +1,102 @@
+use gl::types::*;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// GL_NVX_gpu_memory_info (values in KB)
+const GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX: GLenum = 0x9048;
+const GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX: GLenum = 0x9049;
+// GL_ATI_meminfo (4 values in KB, the first is the total free memory in the pool)
+const TEXTURE_FREE_MEMORY_ATI: GLenum = 0x87FC;
+
+/// Kinds of GPU resources tallied on the CPU side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuResource {
+    Mesh,
+    Texture,
+    Framebuffer,
+}
+
+// Bytes currently uploaded per resource kind (approximate - ignores driver padding)
+static MESH_BYTES: AtomicUsize = AtomicUsize::new(0);
+static TEXTURE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static FRAMEBUFFER_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+fn counter(kind: GpuResource) -> &'static AtomicUsize {
+    match kind {
+        GpuResource::Mesh => &MESH_BYTES,
+        GpuResource::Texture => &TEXTURE_BYTES,
+        GpuResource::Framebuffer => &FRAMEBUFFER_BYTES,
+    }
+}
+
+/// Record bytes uploaded to the GPU (call when a resource is created or reallocated)
+pub fn track_alloc(kind: GpuResource, bytes: usize) {
+    counter(kind).fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Record bytes released on the GPU (call when a resource is dropped or reallocated)
+pub fn track_free(kind: GpuResource, bytes: usize) {
+    // Saturate so a mismatched free can't wrap the tally around
+    let _ = counter(kind).fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        Some(current.saturating_sub(bytes))
+    });
+}
+
+/// Bytes currently tracked for one resource kind
+pub fn allocated_bytes(kind: GpuResource) -> usize {
+    counter(kind).load(Ordering::Relaxed)
+}
+
+/// Bytes currently tracked across all resource kinds
+pub fn total_allocated_bytes() -> usize {
+    allocated_bytes(GpuResource::Mesh)
+        + allocated_bytes(GpuResource::Texture)
+        + allocated_bytes(GpuResource::Framebuffer)
+}
+
+/// Video memory reported by the driver
+#[derive(Debug, Clone, Copy)]
+pub struct VramInfo {
+    pub total_kb: Option<i32>, // Not reported by GL_ATI_meminfo
+    pub available_kb: i32,
+}
+
+/// Query the driver for VRAM stats via GL_NVX_gpu_memory_info or GL_ATI_meminfo
+/// Returns None when neither extension is available (e.g. Intel, Mesa software)
+pub fn query_vram() -> Option<VramInfo> {
+    unsafe {
+        if has_extension("GL_NVX_gpu_memory_info") {
+            let mut total_kb = 0;
+            let mut available_kb = 0;
+            gl::GetIntegerv(GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX, &mut total_kb);
+            gl::GetIntegerv(
+                GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX,
+                &mut available_kb,
+            );
+            return Some(VramInfo {
+                total_kb: Some(total_kb),
+                available_kb,
+            });
+        }
+
+        if has_extension("GL_ATI_meminfo") {
+            let mut info = [0; 4];
+            gl::GetIntegerv(TEXTURE_FREE_MEMORY_ATI, info.as_mut_ptr());
+            return Some(VramInfo {
+                total_kb: None,
+                available_kb: info[0],
+            });
+        }
+    }
+    None
+}
+
+/// Whether the current context advertises an extension, e.g. "GL_ARB_texture_filter_anisotropic"
+pub unsafe fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    (0..count as GLuint).any(|i| {
+        let ext = gl::GetStringi(gl::EXTENSIONS, i);
+        !ext.is_null() && CStr::from_ptr(ext as *const _).to_bytes() == name.as_bytes()
+    })
+}
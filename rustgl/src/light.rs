@@ -1,5 +1,19 @@
 use nalgebra_glm as glm;
 
+/// Shape of a light's emission
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// Shines equally in all directions
+    Point,
+    /// Cone of light: full intensity inside `inner_cutoff`, fading out to `outer_cutoff`
+    /// (half-angles in degrees)
+    Spot {
+        direction: glm::Vec3,
+        inner_cutoff: f32,
+        outer_cutoff: f32,
+    },
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Light {
     pub position: glm::Vec3,
@@ -7,6 +21,7 @@ pub struct Light {
     pub constant: f32,
     pub linear: f32,
     pub quadratic: f32,
+    pub kind: LightKind,
 }
 
 impl Light {
@@ -24,6 +39,7 @@ impl Light {
             constant,
             linear,
             quadratic,
+            kind: LightKind::Point,
         }
     }
 
@@ -34,6 +50,7 @@ impl Light {
             constant: 1.0,
             linear: 0.7,
             quadratic: 1.8,
+            kind: LightKind::Point,
         }
     }
 
@@ -44,6 +61,7 @@ impl Light {
             constant: 1.0,
             linear: 0.35,
             quadratic: 0.44,
+            kind: LightKind::Point,
         }
     }
 
@@ -55,6 +73,7 @@ impl Light {
             constant: 1.0,
             linear: 0.14,
             quadratic: 0.07,
+            kind: LightKind::Point,
         }
     }
 
@@ -66,6 +85,27 @@ impl Light {
             constant: 1.0,
             linear: 0.045,
             quadratic: 0.0075,
+            kind: LightKind::Point,
+        }
+    }
+
+    /// Spotlight with long-range attenuation, pointing along `direction`
+    /// `inner_deg`/`outer_deg` are the cone half-angles where the falloff starts and ends
+    pub fn spot(
+        position: glm::Vec3,
+        direction: glm::Vec3,
+        color: glm::Vec3,
+        inner_deg: f32,
+        outer_deg: f32,
+    ) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                direction: glm::normalize(&direction),
+                inner_cutoff: inner_deg,
+                // Keep the edges apart, the shader's smoothstep needs outer > inner
+                outer_cutoff: outer_deg.max(inner_deg + 0.1),
+            },
+            ..Self::long_range(position, color)
         }
     }
 }
@@ -1,59 +1,142 @@
 extern crate gl;
 extern crate glfw;
 
+mod animator;
 mod bloom_renderer;
 mod camera;
+mod config;
 mod framebuffer;
 mod godray_renderer;
+mod gpu_memory;
 mod light;
 mod material;
 mod mesh;
 mod noise;
 mod performance_monitor;
+mod post_process;
 mod scene;
+mod screenshot;
 mod shader;
 mod terrain;
 mod texture;
 mod transform;
 
-use bloom_renderer::BloomRenderer;
+use animator::Animator;
+use bloom_renderer::{BloomMethod, ToneMapping};
 use camera::{Camera, CameraMovement};
+use config::{Config, WindowConfig};
 use egui::RichText;
 use egui_glfw::egui;
+use framebuffer::Framebuffer;
 use glfw::{Action, Context, Key};
-use godray_renderer::GodRayRenderer;
-use light::Light;
+use gpu_memory::GpuResource;
+use light::{Light, LightKind};
 use material::Material;
 use mesh::Mesh;
 use nalgebra_glm as glm;
 use noise::PerlinNoise;
 use performance_monitor::PerformanceMonitor;
+use post_process::PostProcessStack;
 use scene::{Scene, SceneObjectTag};
+use serde::{Deserialize, Serialize};
 use shader::Shader;
+use std::rc::Rc;
 use std::time::Instant;
-use terrain::Terrain;
-use texture::Texture;
+use terrain::{NoiseType, Terrain};
+use texture::{Texture, TextureOptions};
 use transform::Transform;
 
 // Constants for magic numbers
 const CAMERA_LOOK_SPEED: f32 = 250.0; // degrees per second
 const TARGET_FPS: f32 = 60.0;
 const TARGET_FRAME_TIME: f32 = 1.0 / TARGET_FPS;
+const FRAME_LIMIT_SPIN_TIME: f32 = 0.001; // Spin (instead of sleeping) for the last 1ms of a frame
 const GODRAY_RESOLUTION_SCALE: f32 = 0.75; // Render godrays at 75% resolution for performance
-
+const BLOOM_RESOLUTION_SCALE: f32 = 0.5; // Bloom is a blur anyway, so half resolution is plenty
+const GODRAY_HDR: bool = true; // Float godray buffers, added before tone mapping
+const PERFORMANCE_CSV_PATH: &str = "performance.csv";
+const CONFIG_PATH: &str = "config.toml";
+const CUBE_FIELD_SIZE: usize = 100; // Instancing demo is CUBE_FIELD_SIZE^2 cubes
+
+// Saved to and loaded from the config file; runtime-only fields are skipped
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct AppState {
     wireframe_mode: bool,
+    show_normals: bool, // Draw vertex normals as lines (geometry shader)
     use_texture: bool,
     skybox_enabled: bool,
+    flashlight_enabled: bool,
+    cube_field_enabled: bool, // Instanced rendering demo
+
+    vsync_enabled: bool,
+    msaa_samples: u32, // 0 = off
+    camera_near: f32,
+    camera_far: f32,
+    camera_max_fov: f32,   // Degrees, the zoom-out limit for the scroll wheel
+    camera_smoothing: f32, // Seconds, 0 = instant movement
+    #[serde(skip)]
+    windowed_rect: (i32, i32, i32, i32), // Position and size to restore when leaving fullscreen
+    #[serde(skip)]
+    save_settings_requested: bool,
+    #[serde(skip)]
+    screenshot_requested: bool,
+    #[serde(skip)]
+    composite_capture_requested: bool, // Save the offscreen bloom composite (F12)
+    #[serde(skip)]
+    reload_shaders_requested: bool,
+    #[serde(skip)]
+    shader_errors: Vec<String>, // From the last reload, shown in the debug panel
+    #[serde(skip)]
+    pick_requested: Option<(f64, f64)>, // Cursor position (window coordinates) to pick at
+    #[serde(skip)]
+    selected_object: Option<usize>,
+    #[serde(skip)]
+    mouse_look: bool, // Right mouse button held: cursor captured and steering the camera
+    #[serde(skip)]
+    last_cursor_pos: Option<(f64, f64)>, // None right after capture, so the first delta is skipped
+    screenshot_include_ui: bool, // Capture after the egui pass instead of before it
+
+    // Animation control (the camera keeps moving while paused)
+    #[serde(skip)]
+    paused: bool,
+    #[serde(skip)]
+    step_frame: bool, // Advance the paused animation by one frame
+    frame_limit_enabled: bool, // Redundant while VSync caps the frame rate
+    frame_limit_fps: f32,
 
     bloom_threshold: f32,
+    bloom_threshold_knee: f32,
+    bloom_per_channel: bool,
     bloom_strength: f32,
+    bloom_blur_iterations: usize,
+    bloom_blur_sigma: f32,
+    bloom_method: BloomMethod,
+    bloom_anamorphic: f32,
+    tonemap: ToneMapping,
+    exposure: f32,
+    vignette: f32,
+    gamma_correction: bool,
+    srgb_framebuffer: bool, // Encode with GL_FRAMEBUFFER_SRGB instead of the shader
+    #[serde(skip)]
+    srgb_framebuffer_supported: bool, // Detected at startup
+    chromatic_aberration: f32,
     bloom_enabled: bool,
+    bloom_debug_mode: u8, // 0 = off, 1 = bright pass, 2 = blurred bloom
 
     godray_strength: f32,
     godray_exposure: f32,
     godray_decay: f32,
-    godray_debug_mode: u8, // 0 = off, 1 = occlusion, 2 = radial blur, 3 = rays only
+    godray_density: f32,
+    godray_weight: f32,
+    godray_samples: i32,
+    godray_dither: bool,
+    godray_max_radius: f32,
+    godray_feather: f32,
+    godray_resolution_scale: f32,
+    godray_tint: [f32; 3],
+    godray_depth_occlusion: bool, // Reuse the scene depth instead of a separate occluder pass
+    godray_debug_mode: u8,        // 0 = off, 1 = occlusion, 2 = radial blur, 3 = rays only
 
     // Terrain parameters
     terrain_octaves: u32,
@@ -61,24 +144,78 @@ struct AppState {
     terrain_lacunarity: f32,
     terrain_noise_scale: f32,
     terrain_height_scale: f32,
+    terrain_noise_type: NoiseType,
+    terrain_warp_strength: f32,
+    terrain_grass_color: [f32; 3],
+    terrain_rock_color: [f32; 3],
+    terrain_slope_threshold: f32,
+    #[serde(skip)]
     terrain_needs_regeneration: bool,
-    terrain_index: Option<usize>, // Track terrain object in scene
+    #[serde(skip)]
+    terrain_recolor_requested: bool, // Rebuild the mesh with new colors, keeping the heights
 }
 
 impl AppState {
     fn new() -> Self {
         AppState {
             wireframe_mode: false,
+            show_normals: false,
             use_texture: true,
             skybox_enabled: true,
-
-            bloom_threshold: 0.8,
+            flashlight_enabled: false,
+            cube_field_enabled: false,
+            vsync_enabled: true,
+            msaa_samples: 4,
+            camera_near: 0.1,
+            camera_far: 100.0,
+            camera_max_fov: 90.0,
+            camera_smoothing: 0.0,
+            windowed_rect: (100, 100, 1024, 768),
+            save_settings_requested: false,
+            screenshot_requested: false,
+            composite_capture_requested: false,
+            reload_shaders_requested: false,
+            shader_errors: Vec::new(),
+            pick_requested: None,
+            selected_object: None,
+            mouse_look: false,
+            last_cursor_pos: None,
+            screenshot_include_ui: false,
+            paused: false,
+            step_frame: false,
+            frame_limit_enabled: true,
+            frame_limit_fps: TARGET_FPS,
+
+            bloom_threshold: 1.0,
+            bloom_threshold_knee: 0.5,
+            bloom_per_channel: false,
             bloom_strength: 1.0,
+            bloom_blur_iterations: 5,
+            bloom_blur_sigma: 1.75,
+            bloom_method: BloomMethod::MipChain,
+            bloom_anamorphic: 0.0,
+            tonemap: ToneMapping::Reinhard,
+            exposure: 1.0,
+            vignette: 0.0,
+            gamma_correction: true,
+            srgb_framebuffer: false,
+            srgb_framebuffer_supported: false,
+            chromatic_aberration: 0.0,
             bloom_enabled: true,
+            bloom_debug_mode: 0,
 
             godray_strength: 1.0,
             godray_exposure: 0.5,
             godray_decay: 0.97,
+            godray_density: 0.8,
+            godray_weight: 0.3,
+            godray_samples: 100,
+            godray_dither: true,
+            godray_max_radius: 2.0,
+            godray_feather: 0.5,
+            godray_resolution_scale: GODRAY_RESOLUTION_SCALE,
+            godray_tint: [1.0, 1.0, 1.0],
+            godray_depth_occlusion: true,
             godray_debug_mode: 0,
 
             // Terrain defaults
@@ -87,43 +224,64 @@ impl AppState {
             terrain_lacunarity: 2.0,
             terrain_noise_scale: 0.15,
             terrain_height_scale: 10.0,
+            terrain_noise_type: NoiseType::Fbm,
+            terrain_warp_strength: 0.0,
+            terrain_grass_color: [0.3, 0.55, 0.2],
+            terrain_rock_color: [0.45, 0.42, 0.4],
+            terrain_slope_threshold: 0.3,
             terrain_needs_regeneration: false,
-            terrain_index: None,
+            terrain_recolor_requested: false,
         }
     }
 }
 
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn main() {
+    // Window size, GL version and the initial AppState (defaults if there's no config file)
+    let config = Config::load(CONFIG_PATH);
+
     // Initialize GLFW
     let mut glfw = glfw::init_no_callbacks().expect("Failed to initialize GLFW");
 
-    // Request OpenGL 4.5 Core Profile for Linux
-    // Note: For initial learning steps, we'll start with 3.3 for compatibility
-    // Later steps will upgrade to 4.5+ for advanced features
+    // Request OpenGL Core Profile for Linux (4.5 unless the config file says otherwise)
     #[cfg(target_os = "linux")]
     {
-        glfw.window_hint(glfw::WindowHint::ContextVersion(4, 5));
+        glfw.window_hint(glfw::WindowHint::ContextVersion(
+            config.gl.major,
+            config.gl.minor,
+        ));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(
             glfw::OpenGlProfileHint::Core,
         ));
     }
 
-    // macOS limited to OpenGL 4.1 maximum
+    // macOS limited to OpenGL 4.1 maximum (the config default there)
     #[cfg(target_os = "macos")]
     {
-        glfw.window_hint(glfw::WindowHint::ContextVersion(4, 1));
+        glfw.window_hint(glfw::WindowHint::ContextVersion(
+            config.gl.major,
+            config.gl.minor,
+        ));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(
             glfw::OpenGlProfileHint::Core,
         ));
         glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
     }
 
+    // Ask for an sRGB-capable window so GL_FRAMEBUFFER_SRGB can encode the output
+    glfw.window_hint(glfw::WindowHint::SRgbCapable(true));
+
     // Create a window
     let (mut window, events) = glfw
         .create_window(
-            1024,            // Width
-            768,             // Height
-            "RustGL by mau", // Title
+            config.window.width,  // Width
+            config.window.height, // Height
+            "RustGL by mau",      // Title
             glfw::WindowMode::Windowed,
         )
         .expect("Failed to create GLFW window");
@@ -137,7 +295,9 @@ fn main() {
     window.set_char_polling(true);
 
     // Enable V-Sync to cap FPS at monitor refresh rate (usually 60 FPS)
+    // (toggled at runtime from the debug panel)
     glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+    let mut vsync_applied = true;
 
     // Load OpenGL function pointers
     gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
@@ -172,20 +332,53 @@ fn main() {
     });
     egui_input.input.time = Some(0.01);
 
-    let shader = Shader::new("shader/basic.vert", "shader/basic.frag");
-    // Load a test texture
-    let texture = Texture::new("resources/textures/livia.png").expect("Failed to load texture");
+    let mut shader = Shader::new_or_panic("shader/basic.vert", "shader/basic.frag");
+    let mut normal_shader = Shader::with_geometry(
+        "shader/normals.vert",
+        "shader/normals.geom",
+        "shader/normals.frag",
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+    // Load a test texture (shared by the objects that use it as their diffuse map)
+    let texture = Texture::new_with_options(
+        "resources/textures/livia.png",
+        TextureOptions {
+            anisotropy: 16.0,
+            srgb: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| {
+        // Keep running with an obvious placeholder instead of a missing texture
+        eprintln!("{}", e);
+        Texture::checkerboard(256, 8, [255, 0, 255, 255], [0, 0, 0, 255])
+    });
+    let texture = Rc::new(texture);
 
     // Create performance monitor (60 frame history for smooth averaging)
     let mut perf_monitor = PerformanceMonitor::new(60);
+    // Flag frames that miss the frame-rate target
+    perf_monitor.set_budget(
+        performance_monitor::FRAME_COUNTER,
+        TARGET_FRAME_TIME * 1000.0,
+    );
 
-    // Create bloom renderer (handles all framebuffers and post-processing)
-    let mut bloom_renderer = BloomRenderer::new(fb_width as u32, fb_height as u32);
-    // Create godray renderer with lower resolution for better performance
-    let mut godray_renderer =
-        GodRayRenderer::new(fb_width as u32, fb_height as u32, GODRAY_RESOLUTION_SCALE);
+    // Create post-processing chain (bloom + godrays, owns all framebuffers)
+    // Both effects render at lower resolution for better performance
+    let mut post_process = PostProcessStack::new(
+        fb_width as u32,
+        fb_height as u32,
+        BLOOM_RESOLUTION_SCALE,
+        GODRAY_RESOLUTION_SCALE,
+        GODRAY_HDR,
+    );
 
-    let mut state = AppState::new();
+    let gl_config = config.gl;
+    let mut state = config.settings;
+    state.srgb_framebuffer_supported = Framebuffer::default_is_srgb();
+    if !state.srgb_framebuffer_supported {
+        println!("Window framebuffer isn't sRGB-capable, gamma correction stays in the shader");
+    }
 
     let perlin = PerlinNoise::new(42);
 
@@ -196,6 +389,11 @@ fn main() {
     terrain.lacunarity = state.terrain_lacunarity;
     terrain.noise_scale = state.terrain_noise_scale;
     terrain.height_scale = state.terrain_height_scale;
+    terrain.noise_type = state.terrain_noise_type;
+    terrain.warp_strength = state.terrain_warp_strength;
+    terrain.grass_color = state.terrain_grass_color;
+    terrain.rock_color = state.terrain_rock_color;
+    terrain.slope_threshold = state.terrain_slope_threshold;
     terrain.generate();
 
     // Test terrain height sampling
@@ -231,65 +429,95 @@ fn main() {
     ])
     .expect("Failed to load skybox");
     let skybox_mesh = Mesh::skybox_cube();
-    let skybox_shader = Shader::new("shader/skybox.vert", "shader/skybox.frag");
+    let skybox_shader = Shader::new_or_panic("shader/skybox.vert", "shader/skybox.frag");
     scene.set_skybox(skybox_mesh, skybox_shader, skybox_texture);
 
-    scene.add_object(
+    let ground = scene.add_named_object(
+        "ground",
         Mesh::plane(10.0, 10.0, [0.3, 0.3, 0.3]),
         Material::matte(glm::vec3(0.2, 1.0, 0.3)),
         Transform::from_position(glm::vec3(0.0, -2.0, 0.0)),
     );
+    scene.set_diffuse_map(
+        ground,
+        Some(Rc::new(Texture::checkerboard(
+            256,
+            8,
+            [90, 90, 90, 255],
+            [50, 50, 50, 255],
+        ))),
+    );
+    scene.set_normal_map(ground, Some(Rc::new(noise_normal_map(&perlin, 256, 1.0))));
 
     // Add rotating sphere (left)
-    scene.add_object(
+    let blue_sphere = scene.add_named_object(
+        "blue_sphere",
         Mesh::sphere(1.0, 32, 16, [0.3, 0.7, 1.0]),
         Material::plastic(glm::vec3(0.3, 0.7, 1.0)),
         Transform::from_position(glm::vec3(-4.0, 0.0, 0.0)),
     );
+    scene.set_animator(blue_sphere, Animator::spin(glm::vec3(0.3, 0.5, 0.0)));
 
     // Add rotating cube (center-left)
-    scene.add_object(
+    let orange_cube = scene.add_named_object(
+        "orange_cube",
         Mesh::cube([1.0, 0.5, 0.2]),
         Material::metal(glm::vec3(1.0, 0.5, 0.2)),
         Transform::from_position(glm::vec3(-2.0, 0.0, 0.0)),
     );
+    scene.set_animator(orange_cube, Animator::spin(glm::vec3(0.7, 0.7, 0.0)));
+    scene.set_diffuse_map(orange_cube, Some(Rc::clone(&texture)));
 
     // Add rotating cylinder (center)
-    scene.add_object(
+    let green_cylinder = scene.add_named_object(
+        "green_cylinder",
         Mesh::cylinder(0.5, 2.0, 32, [0.2, 1.0, 0.3]),
         Material::matte(glm::vec3(0.2, 1.0, 0.3)),
         Transform::from_position(glm::vec3(0.0, 0.0, 0.0)),
     );
+    scene.set_animator(green_cylinder, Animator::spin(glm::vec3(0.3, 0.4, 0.0)));
+    scene.set_diffuse_map(green_cylinder, Some(Rc::clone(&texture)));
 
     // Add rotating torus (center-right)
-    scene.add_object(
+    let pink_torus = scene.add_named_object(
+        "pink_torus",
         Mesh::torus(1.0, 0.3, 32, 16, [1.0, 0.3, 0.7]),
         Material::rubber(glm::vec3(1.0, 0.3, 0.7)),
         Transform::from_position(glm::vec3(2.0, 0.0, 0.0)),
     );
+    scene.set_animator(pink_torus, Animator::spin(glm::vec3(0.3, 0.6, 0.0)));
 
     // Add small chrome sphere (right)
-    scene.add_object(
+    let chrome_sphere = scene.add_named_object(
+        "chrome_sphere",
         Mesh::sphere(1.0, 32, 16, [0.8, 0.8, 0.8]),
         Material::chrome(),
         Transform::from_position_scale(glm::vec3(4.0, 0.0, 0.0), glm::vec3(0.8, 0.8, 0.8)),
     );
+    scene.set_animator(chrome_sphere, Animator::spin(glm::vec3(0.4, 0.8, 0.4)));
+
+    // Pivot high above the scene with no geometry of its own; spinning it carries its
+    // children around in a circle
+    let orb_pivot = scene.add_named_object(
+        "orb_pivot",
+        Mesh::new(&[]),
+        Material::matte(glm::vec3(1.0, 1.0, 1.0)),
+        Transform::from_position(glm::vec3(0.0, 12.0, 0.0)),
+    );
+    scene.set_animator(orb_pivot, Animator::spin(glm::vec3(0.0, 0.5, 0.0)));
 
-    // Add orbiting light sphere (bright white, small)
+    // Add orbiting light sphere (bright white, small), a child of the pivot
     // This will be used as the godray source
-    scene.add_object(
+    let light_orb = scene.add_named_object(
+        "light_orb",
         Mesh::sphere(1.0, 16, 8, [1.0, 1.0, 1.0]),
-        Material::new(
-            glm::vec3(1.0, 1.0, 1.0), // High ambient (self-illuminated look)
-            glm::vec3(1.0, 1.0, 1.0), // White diffuse
-            glm::vec3(1.0, 1.0, 1.0), // White specular
-            32.0,                     // Shininess
-        ),
-        Transform::from_position_scale(glm::vec3(6.0, 2.0, 0.0), glm::vec3(0.3, 0.3, 0.3)),
+        // Glows on its own so it blooms no matter how the scene lights hit it
+        Material::emissive(glm::vec3(1.0, 1.0, 1.0), 4.0),
+        Transform::from_position_scale(glm::vec3(6.0, 0.0, 0.0), glm::vec3(0.3, 0.3, 0.3)),
     );
-    // Tag the last object (orbiting light sphere) as the godray source
-    let orb_index = scene.object_count() - 1;
-    scene.tag_object(orb_index, SceneObjectTag::GodraySource);
+    scene.set_parent(light_orb, Some(orb_pivot));
+    // Tag the orbiting light sphere as the godray source
+    scene.tag_object(light_orb, SceneObjectTag::GodraySource);
 
     // Add static lights
     scene.add_light(Light::medium_range(
@@ -311,16 +539,52 @@ fn main() {
         glm::vec3(10.0, 10.0, 10.0), // Very bright white light
     ));
 
-    // Add terrain to scene and track its index
-    let terrain_index = scene.object_count();
-    scene.add_object(
+    // The light sphere orbits with its pivot, carrying the light with it
+    scene.set_animator(
+        light_orb,
+        Animator::default().with_light(scene.lights().len() - 1),
+    );
+
+    // Flashlight spotlight, follows the camera every frame (dark while switched off)
+    let flashlight_index = scene.lights().len();
+    scene.add_light(Light::spot(
+        glm::vec3(0.0, 0.0, 0.0),
+        glm::vec3(0.0, 0.0, -1.0),
+        glm::vec3(0.0, 0.0, 0.0),
+        12.5,
+        17.5,
+    ));
+
+    // Add terrain to scene (found again by name when it's regenerated)
+    scene.add_named_object(
+        "terrain",
         terrain.create_mesh(), // Generate a mesh on demand
         Material::matte(glm::vec3(0.4, 0.6, 0.3)),
         Transform::from_position(glm::vec3(0.0, 0.0, 0.0)),
     );
-    state.terrain_index = Some(terrain_index);
+
+    // Instancing demo: a wavy 100x100 field of cubes floating above the terrain, one draw call
+    let mut cube_transforms = Vec::with_capacity(CUBE_FIELD_SIZE * CUBE_FIELD_SIZE);
+    for row in 0..CUBE_FIELD_SIZE {
+        for col in 0..CUBE_FIELD_SIZE {
+            let x = (col as f32 - CUBE_FIELD_SIZE as f32 / 2.0) * 0.6;
+            let z = (row as f32 - CUBE_FIELD_SIZE as f32 / 2.0) * 0.6 - 40.0;
+            let y = 6.0 + (x * 0.2).sin() * (z * 0.2).cos() * 2.0;
+            cube_transforms.push(Transform::from_position_scale(
+                glm::vec3(x, y, z),
+                glm::vec3(0.4, 0.4, 0.4),
+            ));
+        }
+    }
+    let cube_field = scene.add_instanced(
+        Mesh::cube([0.9, 0.7, 0.3]),
+        Material::plastic(glm::vec3(0.9, 0.7, 0.3)),
+        cube_transforms,
+    );
 
     let mut camera = Camera::default();
+    // Config::sanitize already keeps these valid; the UI sliders stay within the same limits
+    let mut camera_limits_applied = None;
 
     let mut last_frame_time = glfw.get_time() as f32;
     let mut frame_count = 0;
@@ -329,11 +593,21 @@ fn main() {
 
     // Window loop - keep the window open
     while !window.should_close() {
+        // Apply VSync changes from the UI
+        if state.vsync_enabled != vsync_applied {
+            glfw.set_swap_interval(if state.vsync_enabled {
+                glfw::SwapInterval::Sync(1)
+            } else {
+                glfw::SwapInterval::None
+            });
+            vsync_applied = state.vsync_enabled;
+        }
+
         // Frame timing - wait until target frame time has elapsed
-        let mut delta_time = glfw.get_time() as f32 - last_frame_time;
-        while delta_time < TARGET_FRAME_TIME {
-            delta_time = glfw.get_time() as f32 - last_frame_time;
+        if state.frame_limit_enabled {
+            wait_for_next_frame(&glfw, last_frame_time, 1.0 / state.frame_limit_fps);
         }
+        let delta_time = glfw.get_time() as f32 - last_frame_time;
         last_frame_time = glfw.get_time() as f32;
 
         frame_count += 1;
@@ -359,59 +633,154 @@ fn main() {
             &events,
             &mut camera,
             &mut state,
-            &mut bloom_renderer,
-            &mut godray_renderer,
+            &mut post_process,
             &mut egui_painter,
             &mut egui_input,
             &egui_ctx,
             delta_time,
         );
-        update(delta_time, &mut time, &mut scene);
+        // Apply near/far/FOV limit changes from the UI (and the config on the first frame)
+        let camera_limits = (state.camera_near, state.camera_far, state.camera_max_fov);
+        if camera_limits_applied != Some(camera_limits) {
+            camera.near = state.camera_near;
+            camera.far = state.camera_far;
+            camera.max_zoom = state.camera_max_fov;
+            camera.zoom = camera.zoom.clamp(camera.min_zoom, camera.max_zoom);
+            camera_limits_applied = Some(camera_limits);
+        }
+        // Camera eases toward its input targets even while the scene is paused
+        camera.smoothing = state.camera_smoothing;
+        camera.update(delta_time);
+        if !state.paused {
+            update(delta_time, &mut time, &mut scene);
+        } else if state.step_frame {
+            // Fixed step so stepping looks the same regardless of the current frame rate
+            update(TARGET_FRAME_TIME, &mut time, &mut scene);
+            state.step_frame = false;
+        }
+
+        if state.reload_shaders_requested {
+            let mut shaders = vec![&mut shader, &mut normal_shader];
+            shaders.extend(scene.shaders_mut());
+            shaders.extend(post_process.shaders_mut());
+            state.shader_errors = reload_shaders(shaders);
+            state.reload_shaders_requested = false;
+        }
+
+        if let Some((cursor_x, cursor_y)) = state.pick_requested.take() {
+            // Cursor positions are in window coordinates, not framebuffer pixels (high-DPI)
+            let (width, height) = window.get_size();
+            let (origin, dir) = camera.screen_ray(
+                cursor_x as f32,
+                cursor_y as f32,
+                width as f32,
+                height as f32,
+                width as f32 / height as f32,
+            );
+            state.selected_object = scene.pick(origin, dir);
+        }
+        scene.set_selected(state.selected_object);
+        if let Some(flashlight) = scene.get_light_mut(flashlight_index) {
+            flashlight.position = camera.position;
+            if let LightKind::Spot { direction, .. } = &mut flashlight.kind {
+                *direction = camera.front;
+            }
+            flashlight.color = if state.flashlight_enabled {
+                glm::vec3(3.0, 3.0, 2.6) // Slightly warm white
+            } else {
+                glm::vec3(0.0, 0.0, 0.0)
+            };
+        }
+        scene.set_instanced_visible(cube_field, state.cube_field_enabled);
 
-        // Reset performance counters for the new frame
+        // Reset performance counters for the new frame and start the whole-frame timer
         // This ensures disabled passes show 0ms instead of stale data
-        perf_monitor.reset_frame();
+        perf_monitor.begin_frame();
 
         // Render scene with bloom post-processing
         let (fb_width, fb_height) = window.get_framebuffer_size();
         let aspect_ratio = fb_width as f32 / fb_height as f32;
 
-        bloom_renderer.render(
+        // Update bloom parameters from UI state
+        let bloom_renderer = &mut post_process.bloom;
+        bloom_renderer.set_blur_iterations(state.bloom_blur_iterations);
+        bloom_renderer.set_blur_sigma(state.bloom_blur_sigma);
+        bloom_renderer.set_method(state.bloom_method);
+        bloom_renderer.set_threshold_knee(state.bloom_threshold_knee);
+        bloom_renderer.set_per_channel_threshold(state.bloom_per_channel);
+        bloom_renderer.set_anamorphic(state.bloom_anamorphic);
+        bloom_renderer.set_tonemap(state.tonemap, state.exposure);
+        bloom_renderer.set_vignette(state.vignette);
+        // Hardware sRGB output takes over the encoding so it isn't applied twice
+        let hardware_srgb =
+            state.gamma_correction && state.srgb_framebuffer && state.srgb_framebuffer_supported;
+        bloom_renderer.set_gamma_correction(state.gamma_correction && !hardware_srgb);
+        bloom_renderer.set_chromatic_aberration(state.chromatic_aberration);
+        bloom_renderer.set_debug_mode(state.bloom_debug_mode);
+        bloom_renderer.set_msaa_samples(state.msaa_samples);
+        let scene_depth = bloom_renderer.scene_depth_texture();
+        post_process.srgb_output = hardware_srgb;
+        post_process.bloom_enabled = state.bloom_enabled;
+        post_process.bloom_threshold = state.bloom_threshold;
+        post_process.bloom_strength = state.bloom_strength;
+
+        // Update godray parameters from UI state
+        let godray_renderer = &mut post_process.godrays;
+        godray_renderer.exposure = state.godray_exposure;
+        godray_renderer.decay = state.godray_decay;
+        godray_renderer.density = state.godray_density;
+        godray_renderer.weight = state.godray_weight;
+        godray_renderer.num_samples = state.godray_samples;
+        godray_renderer.dither = state.godray_dither;
+        godray_renderer.set_max_radius(state.godray_max_radius);
+        godray_renderer.set_feather(state.godray_feather);
+        godray_renderer.set_resolution_scale(state.godray_resolution_scale);
+        godray_renderer.set_tint(glm::make_vec3(&state.godray_tint));
+        godray_renderer.set_scene_depth(if state.godray_depth_occlusion {
+            scene_depth
+        } else {
+            None
+        });
+        post_process.godray_strength = state.godray_strength;
+        post_process.godray_debug_mode = state.godray_debug_mode;
+
+        // Render scene through bloom and godrays to the screen
+        let view = camera.get_view_matrix();
+        let projection = camera.get_projection_matrix(aspect_ratio);
+        post_process.render(
             || {
-                render_scene(&scene, &shader, &texture, &camera, &state, aspect_ratio);
+                render_scene(
+                    &scene,
+                    &shader,
+                    &normal_shader,
+                    &camera,
+                    &state,
+                    &projection,
+                );
             },
-            state.bloom_threshold,
-            state.bloom_strength,
-            state.bloom_enabled,
-            fb_width,
-            fb_height,
+            &scene,
+            &view,
+            &projection,
             &mut perf_monitor,
         );
-
-        // In render loop - after bloom
-        // Find the godray source object by tag instead of hardcoded index
-        if let Some(orb_idx) = scene.find_object_by_tag(SceneObjectTag::GodraySource) {
-            let light_pos = scene.lights()[3].position;
-            let view = camera.get_view_matrix();
-            let projection = glm::perspective(aspect_ratio, camera.zoom.to_radians(), 0.1, 100.0);
-
-            // Update godray parameters from UI state
-            godray_renderer.exposure = state.godray_exposure;
-            godray_renderer.decay = state.godray_decay;
-
-            godray_renderer.apply(
-                bloom_renderer.composite_texture(),
-                &scene,
-                orb_idx,
-                light_pos,
-                &view,
-                &projection,
-                state.godray_strength,
-                state.godray_debug_mode,
-                fb_width,
-                fb_height,
-                &mut perf_monitor,
+        perf_monitor.set_frame_stat("Culled Objects", scene.culled_count());
+
+        // Tone-mapped offscreen composite, before screen-space god rays and the UI
+        if state.composite_capture_requested {
+            let composite = post_process.bloom.composite_framebuffer();
+            save_capture(
+                &screenshot::timestamped_path("composite"),
+                composite.width(),
+                composite.height(),
+                composite.read_pixels(),
             );
+            state.composite_capture_requested = false;
+        }
+
+        // Screenshot of the final post-processed frame, without the UI
+        if state.screenshot_requested && !state.screenshot_include_ui {
+            take_screenshot(&window);
+            state.screenshot_requested = false;
         }
 
         // Render UI
@@ -425,9 +794,6 @@ fn main() {
             egui::vec2(width as f32, height as f32),
         ));
 
-        // Update performance monitor (collect GPU timer results)
-        perf_monitor.update();
-
         // Check if terrain needs regeneration
         if state.terrain_needs_regeneration {
             terrain.octaves = state.terrain_octaves;
@@ -435,21 +801,52 @@ fn main() {
             terrain.lacunarity = state.terrain_lacunarity;
             terrain.noise_scale = state.terrain_noise_scale;
             terrain.height_scale = state.terrain_height_scale;
+            terrain.noise_type = state.terrain_noise_type;
+            terrain.warp_strength = state.terrain_warp_strength;
             terrain.regenerate();
+        }
+
+        if state.terrain_needs_regeneration || state.terrain_recolor_requested {
+            terrain.grass_color = state.terrain_grass_color;
+            terrain.rock_color = state.terrain_rock_color;
+            terrain.slope_threshold = state.terrain_slope_threshold;
 
             // Replace terrain mesh in scene
-            if let Some(terrain_idx) = state.terrain_index {
-                if let Some(obj) = scene.get_object_mut(terrain_idx) {
-                    obj.replace_mesh(terrain.create_mesh());
-                }
+            if let Some(obj) = scene.get_by_name_mut("terrain") {
+                obj.replace_mesh(terrain.create_mesh());
             }
 
             state.terrain_needs_regeneration = false;
+            state.terrain_recolor_requested = false;
+        }
+
+        if state.save_settings_requested {
+            // Save the windowed size even while fullscreen, so the next run starts windowed
+            let (width, height) = if window
+                .with_window_mode(|mode| matches!(mode, glfw::WindowMode::FullScreen(_)))
+            {
+                (state.windowed_rect.2, state.windowed_rect.3)
+            } else {
+                window.get_size()
+            };
+            let config = Config {
+                window: WindowConfig {
+                    width: width as u32,
+                    height: height as u32,
+                },
+                gl: gl_config,
+                settings: state.clone(),
+            };
+            match config.save(CONFIG_PATH) {
+                Ok(()) => println!("Settings saved to {}", CONFIG_PATH),
+                Err(e) => eprintln!("{}", e),
+            }
+            state.save_settings_requested = false;
         }
 
         egui_ctx.begin_frame(egui_input.input.take());
         render_ui(&egui_ctx, &mut state, delta_time, frame_count, &camera);
-        render_performance_ui(&egui_ctx, &perf_monitor, delta_time);
+        render_performance_ui(&egui_ctx, &mut perf_monitor, delta_time);
 
         let egui::FullOutput {
             platform_output,
@@ -478,7 +875,18 @@ fn main() {
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
 
+        perf_monitor.begin("9. Egui UI");
         egui_painter.paint_and_update_textures(pixels_per_point, &clipped_shapes, &textures_delta);
+        perf_monitor.end("9. Egui UI");
+
+        if state.screenshot_requested {
+            take_screenshot(&window);
+            state.screenshot_requested = false;
+        }
+
+        // Close the frame timer and update performance monitor (collect GPU timer results)
+        perf_monitor.end_frame();
+        perf_monitor.update();
 
         window.swap_buffers();
     }
@@ -489,8 +897,7 @@ fn process_events(
     events: &glfw::GlfwReceiver<(f64, glfw::WindowEvent)>,
     camera: &mut Camera,
     state: &mut AppState,
-    bloom_renderer: &mut BloomRenderer,
-    godray_renderer: &mut GodRayRenderer,
+    post_process: &mut PostProcessStack,
     egui_painter: &mut egui_glfw::Painter,
     egui_input: &mut egui_glfw::EguiInputState,
     egui_ctx: &egui::Context,
@@ -505,27 +912,74 @@ fn process_events(
                 window.set_should_close(true);
             }
             glfw::WindowEvent::FramebufferSize(width, height) => {
-                bloom_renderer.resize(width as u32, height as u32);
-                godray_renderer.resize(width as u32, height as u32);
-
-                let (win_width, win_height) = window.get_size();
+                resize_framebuffers(
+                    window,
+                    post_process,
+                    egui_painter,
+                    egui_input,
+                    width,
+                    height,
+                );
+            }
+            glfw::WindowEvent::Key(key, _, action, _) => {
+                handle_key_event(key, action, state, window);
 
-                unsafe {
-                    gl::Viewport(0, 0, width, height);
+                // Switching monitors changes the framebuffer size; resize right away rather
+                // than rendering a frame at the old size before the resize event arrives
+                if (key, action) == (Key::F11, Action::Press) {
+                    let (width, height) = window.get_framebuffer_size();
+                    resize_framebuffers(
+                        window,
+                        post_process,
+                        egui_painter,
+                        egui_input,
+                        width,
+                        height,
+                    );
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, mods) => {
+                // Clicks on the UI belong to egui; anything else selects an object
+                if !egui_ctx.wants_pointer_input() {
+                    state.pick_requested = Some(window.get_cursor_pos());
                 }
-
-                // Update egui painter canvas size (physical pixels)
-                egui_painter.set_size(width as u32, height as u32);
-
-                // IMPORTANT: Let egui_glfw handle resize to update screen_rect
-                // Pass window size (logical pixels), not framebuffer size
                 egui_glfw::handle_event(
-                    glfw::WindowEvent::FramebufferSize(win_width, win_height),
+                    glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, mods),
                     egui_input,
                 );
             }
-            glfw::WindowEvent::Key(key, _, action, _) => {
-                handle_key_event(key, action, state, window);
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, action, mods) => {
+                match action {
+                    Action::Press if !egui_ctx.wants_pointer_input() => {
+                        state.mouse_look = true;
+                        state.last_cursor_pos = None;
+                        window.set_cursor_mode(glfw::CursorMode::Disabled);
+                    }
+                    Action::Release if state.mouse_look => {
+                        state.mouse_look = false;
+                        window.set_cursor_mode(glfw::CursorMode::Normal);
+                    }
+                    _ => {}
+                }
+                egui_glfw::handle_event(
+                    glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, action, mods),
+                    egui_input,
+                );
+            }
+            glfw::WindowEvent::Scroll(x, y) => {
+                if egui_ctx.wants_pointer_input() {
+                    egui_glfw::handle_event(glfw::WindowEvent::Scroll(x, y), egui_input);
+                } else {
+                    camera.process_mouse_scroll(y as f32);
+                }
+            }
+            glfw::WindowEvent::CursorPos(x, y) if state.mouse_look => {
+                // The cursor jumps when it gets captured, so only track deltas after that
+                if let Some((last_x, last_y)) = state.last_cursor_pos {
+                    // Window Y grows downwards, pitch grows upwards
+                    camera.process_mouse_movement((x - last_x) as f32, (last_y - y) as f32, true);
+                }
+                state.last_cursor_pos = Some((x, y));
             }
             glfw::WindowEvent::CursorPos(x, y) => {
                 // Let egui_glfw handle cursor events normally (expects window coordinates)
@@ -578,78 +1032,173 @@ fn process_events(
     }
 }
 
-fn handle_key_event(key: Key, action: Action, _state: &mut AppState, window: &mut glfw::Window) {
-    match (key, action) {
-        (Key::Escape, Action::Press) => {
-            window.set_should_close(true);
+/// Generate a bumpy tangent-space normal map from fractal noise heights
+/// `strength` scales the slopes (0 = flat)
+fn noise_normal_map(perlin: &PerlinNoise, size: u32, strength: f32) -> Texture {
+    const FREQUENCY: f32 = 16.0; // Noise features across the texture
+    let height = |x: u32, y: u32| {
+        let (u, v) = (x as f32 / size as f32, y as f32 / size as f32);
+        perlin.fractal_noise(u * FREQUENCY, v * FREQUENCY, 4, 0.5, 2.0)
+    };
+
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            // Central differences, clamped at the edges, in height per texel
+            let dx = height((x + 1).min(size - 1), y) - height(x.saturating_sub(1), y);
+            let dy = height(x, (y + 1).min(size - 1)) - height(x, y.saturating_sub(1));
+            let scale = strength * size as f32 / FREQUENCY;
+            let normal = glm::normalize(&glm::vec3(-dx * scale, -dy * scale, 1.0));
+            for component in [normal.x, normal.y, normal.z] {
+                pixels.push(((component * 0.5 + 0.5) * 255.0).round() as u8);
+            }
+            pixels.push(255);
         }
-        _ => {}
     }
-}
 
-fn update(delta_time: f32, time: &mut f32, scene: &mut Scene) {
-    // Game logic
-    *time += delta_time;
+    Texture::from_rgba(size, size, &pixels).expect("Normal map size mismatch")
+}
 
-    // Animate objects by updating their transforms
-    // Object indices: 0=plane, 1=sphere, 2=cube, 3=cylinder, 4=torus, 5=chrome sphere, 6=orbiting light sphere
+/// Save the current back buffer to a PNG
+fn take_screenshot(window: &glfw::Window) {
+    let (width, height) = window.get_framebuffer_size();
+    let (width, height) = (width as u32, height as u32);
+    save_capture(
+        &screenshot::timestamped_path("screenshot"),
+        width,
+        height,
+        screenshot::capture_screen(width, height),
+    );
+}
 
-    if let Some(sphere) = scene.get_object_mut(1) {
-        sphere.transform.rotate(0.0, 0.5 * delta_time, 0.0);
-        sphere.transform.rotate_x(0.3 * delta_time);
+/// Write read-back pixels to `path` and report the outcome
+fn save_capture(path: &str, width: u32, height: u32, pixels: Vec<u8>) {
+    match screenshot::save_screenshot(path, width, height, pixels) {
+        Ok(()) => println!("Screenshot saved to {}", path),
+        Err(e) => eprintln!("{}", e),
     }
+}
 
-    if let Some(cube) = scene.get_object_mut(2) {
-        cube.transform
-            .rotate(0.7 * delta_time, 0.7 * delta_time, 0.0);
-    }
+/// Resize everything that depends on the framebuffer size
+fn resize_framebuffers(
+    window: &glfw::Window,
+    post_process: &mut PostProcessStack,
+    egui_painter: &mut egui_glfw::Painter,
+    egui_input: &mut egui_glfw::EguiInputState,
+    width: i32,
+    height: i32,
+) {
+    post_process.resize(width as u32, height as u32);
+
+    let (win_width, win_height) = window.get_size();
 
-    if let Some(cylinder) = scene.get_object_mut(3) {
-        cylinder
-            .transform
-            .rotate(0.3 * delta_time, 0.4 * delta_time, 0.0);
+    unsafe {
+        gl::Viewport(0, 0, width, height);
     }
 
-    if let Some(torus) = scene.get_object_mut(4) {
-        torus.transform.rotate(0.0, 0.6 * delta_time, 0.0);
-        torus.transform.rotate_x(0.6 * delta_time * 0.5);
+    // Update egui painter canvas size (physical pixels)
+    egui_painter.set_size(width as u32, height as u32);
+
+    // IMPORTANT: Let egui_glfw handle resize to update screen_rect
+    // Pass window size (logical pixels), not framebuffer size
+    egui_glfw::handle_event(
+        glfw::WindowEvent::FramebufferSize(win_width, win_height),
+        egui_input,
+    );
+}
+
+fn handle_key_event(key: Key, action: Action, state: &mut AppState, window: &mut glfw::Window) {
+    match (key, action) {
+        (Key::Escape, Action::Press) => {
+            window.set_should_close(true);
+        }
+        (Key::F11, Action::Press) => {
+            toggle_fullscreen(state, window);
+        }
+        (Key::F2, Action::Press) => {
+            // Captured later in the frame, once the final composite has been drawn
+            state.screenshot_requested = true;
+        }
+        (Key::F12, Action::Press) => {
+            state.composite_capture_requested = true;
+        }
+        (Key::F, Action::Press) => {
+            state.flashlight_enabled = !state.flashlight_enabled;
+        }
+        (Key::F5, Action::Press) => {
+            state.reload_shaders_requested = true;
+        }
+        (Key::Space, Action::Press) => {
+            state.paused = !state.paused;
+        }
+        (Key::Period, Action::Press | Action::Repeat) => {
+            // Stepping implies pausing, so the step isn't lost in a running animation
+            state.paused = true;
+            state.step_frame = true;
+        }
+        _ => {}
     }
+}
 
-    if let Some(chrome_sphere) = scene.get_object_mut(5) {
-        chrome_sphere.transform.rotate(
-            0.8 * delta_time * 0.5,
-            0.8 * delta_time,
-            0.8 * delta_time * 0.5,
+/// Switch between windowed mode and fullscreen on the primary monitor
+fn toggle_fullscreen(state: &mut AppState, window: &mut glfw::Window) {
+    let is_fullscreen =
+        window.with_window_mode(|mode| matches!(mode, glfw::WindowMode::FullScreen(_)));
+
+    if is_fullscreen {
+        // Restore the window where it was before going fullscreen
+        let (x, y, width, height) = state.windowed_rect;
+        window.set_monitor(
+            glfw::WindowMode::Windowed,
+            x,
+            y,
+            width as u32,
+            height as u32,
+            None,
         );
+        return;
     }
 
-    // Update orbiting light sphere position
-    let orbit_radius = 6.0;
-    let orbit_speed = 0.5; // radians per second
-    let orbit_height = 12.0;
-    let angle = *time * orbit_speed;
-
-    let light_pos = glm::vec3(
-        angle.cos() * orbit_radius,
-        orbit_height,
-        angle.sin() * orbit_radius,
-    );
+    let (x, y) = window.get_pos();
+    let (width, height) = window.get_size();
+
+    let mut glfw = window.glfw.clone();
+    glfw.with_primary_monitor(|_, monitor| {
+        let Some(monitor) = monitor else {
+            eprintln!("No primary monitor found; staying windowed");
+            return;
+        };
+        let Some(mode) = monitor.get_video_mode() else {
+            eprintln!("Primary monitor has no video mode; staying windowed");
+            return;
+        };
+        state.windowed_rect = (x, y, width, height);
+        window.set_monitor(
+            glfw::WindowMode::FullScreen(monitor),
+            0,
+            0,
+            mode.width,
+            mode.height,
+            Some(mode.refresh_rate),
+        );
+    });
+}
 
-    if let Some(light_sphere) = scene.get_object_mut(6) {
-        light_sphere.transform.position = light_pos;
-    }
+fn update(delta_time: f32, time: &mut f32, scene: &mut Scene) {
+    // Game logic
+    *time += delta_time;
 
-    // Update the orbiting light position to match the sphere
-    scene.update_light_position(3, light_pos);
+    // Animate objects by updating their transforms (see the animators set up in main)
+    scene.update_animations(delta_time, *time);
 }
 
 fn render_scene(
     scene: &Scene,
     shader: &Shader,
-    texture: &Texture,
+    normal_shader: &Shader,
     camera: &Camera,
     state: &AppState,
-    aspect_ratio: f32,
+    projection: &glm::Mat4,
 ) {
     unsafe {
         gl::Enable(gl::DEPTH_TEST);
@@ -664,17 +1213,58 @@ fn render_scene(
         }
 
         let view = camera.get_view_matrix();
-        let projection = glm::perspective(aspect_ratio, camera.zoom.to_radians(), 0.1, 100.0);
 
         // Set up scene shader uniforms before rendering
         shader.use_program();
         shader.set_vec3("viewPos", &camera.position);
-        texture.bind(0);
-        shader.set_int("textureSampler", 0);
-        shader.set_bool("useTexture", state.use_texture);
 
-        // Scene renders skybox internally, then objects
-        scene.render(&shader, &view, &projection, state.skybox_enabled);
+        // Scene renders skybox internally, then objects (binding each one's texture)
+        scene.render(
+            &shader,
+            &view,
+            projection,
+            state.skybox_enabled,
+            state.use_texture,
+        );
+
+        if state.show_normals {
+            scene.render_normals(normal_shader, &view, projection, 0.2);
+        }
+    }
+}
+
+/// Rebuild shaders from their source files, keeping the old program for any that fail
+/// Returns the error messages of the shaders that failed
+fn reload_shaders(shaders: Vec<&mut Shader>) -> Vec<String> {
+    let total = shaders.len();
+    let mut errors = Vec::new();
+    for shader in shaders {
+        if let Err(e) = shader.reload() {
+            let message = format!(
+                "Keeping previous version of {}: {}",
+                shader.source_paths().join(" + "),
+                e
+            );
+            eprintln!("{}", message);
+            errors.push(message);
+        }
+    }
+    println!("Reloaded {} of {} shaders", total - errors.len(), total);
+    errors
+}
+
+/// Block until `target_frame_time` seconds have passed since `last_frame_time`
+/// Sleeps for most of the wait and only spins for the final stretch, since sleep can
+/// overshoot by around a millisecond
+fn wait_for_next_frame(glfw: &glfw::Glfw, last_frame_time: f32, target_frame_time: f32) {
+    let remaining = target_frame_time - (glfw.get_time() as f32 - last_frame_time);
+    if remaining > FRAME_LIMIT_SPIN_TIME {
+        std::thread::sleep(std::time::Duration::from_secs_f32(
+            remaining - FRAME_LIMIT_SPIN_TIME,
+        ));
+    }
+    while (glfw.get_time() as f32 - last_frame_time) < target_frame_time {
+        std::hint::spin_loop();
     }
 }
 
@@ -706,6 +1296,39 @@ fn render_ui(
                 "Position: ({:.1}, {:.1}, {:.1})",
                 camera.position.x, camera.position.y, camera.position.z
             ));
+            ui.horizontal(|ui| {
+                match state.selected_object {
+                    Some(index) => ui.label(format!("Selected: object {}", index)),
+                    None => ui.label("Selected: none"),
+                };
+                if ui
+                    .add_enabled(state.selected_object.is_some(), egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    state.selected_object = None;
+                }
+            });
+            ui.add(
+                egui::Slider::new(&mut state.camera_smoothing, 0.0..=0.5)
+                    .text("Smoothing")
+                    .suffix("s"),
+            );
+            ui.label(format!("FOV: {:.1}°", camera.zoom));
+            ui.add(
+                egui::Slider::new(&mut state.camera_max_fov, 10.0..=120.0)
+                    .text("Max FOV")
+                    .suffix("°"),
+            );
+            ui.add(
+                egui::Slider::new(&mut state.camera_near, 0.01..=5.0)
+                    .logarithmic(true)
+                    .text("Near Plane"),
+            );
+            ui.add(
+                egui::Slider::new(&mut state.camera_far, 10.0..=1000.0)
+                    .logarithmic(true)
+                    .text("Far Plane"),
+            );
 
             ui.add_space(10.0);
 
@@ -713,8 +1336,48 @@ fn render_ui(
             ui.heading("Rendering");
             ui.separator();
             ui.checkbox(&mut state.wireframe_mode, "Wireframe Mode");
+            ui.checkbox(&mut state.show_normals, "Show Normals");
             ui.checkbox(&mut state.use_texture, "Use Textures");
             ui.checkbox(&mut state.skybox_enabled, "Skybox");
+            ui.checkbox(&mut state.flashlight_enabled, "Flashlight");
+            ui.checkbox(
+                &mut state.cube_field_enabled,
+                format!(
+                    "Instanced Cube Field ({} cubes)",
+                    CUBE_FIELD_SIZE * CUBE_FIELD_SIZE
+                ),
+            );
+            ui.horizontal(|ui| {
+                ui.label("MSAA:");
+                ui.radio_value(&mut state.msaa_samples, 0, "Off");
+                ui.radio_value(&mut state.msaa_samples, 2, "2x");
+                ui.radio_value(&mut state.msaa_samples, 4, "4x");
+                ui.radio_value(&mut state.msaa_samples, 8, "8x");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut state.paused, "Pause Animation");
+                if ui
+                    .add_enabled(state.paused, egui::Button::new("Step"))
+                    .clicked()
+                {
+                    state.step_frame = true;
+                }
+            });
+            ui.checkbox(&mut state.vsync_enabled, "VSync");
+            ui.checkbox(&mut state.frame_limit_enabled, "Frame Limiter");
+            if state.frame_limit_enabled {
+                ui.add(
+                    egui::Slider::new(
+                        &mut state.frame_limit_fps,
+                        config::MIN_FRAME_LIMIT_FPS..=config::MAX_FRAME_LIMIT_FPS,
+                    )
+                    .logarithmic(true)
+                    .text("Target FPS"),
+                );
+            }
+            if !state.vsync_enabled && !state.frame_limit_enabled {
+                ui.label("Uncapped - rendering as fast as possible");
+            }
 
             ui.add_space(10.0);
 
@@ -724,12 +1387,68 @@ fn render_ui(
             ui.checkbox(&mut state.bloom_enabled, "Enable Bloom");
 
             if state.bloom_enabled {
-                ui.add(egui::Slider::new(&mut state.bloom_threshold, 0.0..=2.0).text("Threshold"));
+                ui.add(egui::Slider::new(&mut state.bloom_threshold, 0.0..=5.0).text("Threshold"));
+                ui.add(
+                    egui::Slider::new(&mut state.bloom_threshold_knee, 0.0..=2.0).text("Soft Knee"),
+                );
+                ui.checkbox(&mut state.bloom_per_channel, "Per-Channel Threshold");
+                ui.add(
+                    egui::Slider::new(&mut state.bloom_anamorphic, 0.0..=2.0)
+                        .text("Anamorphic Streaks"),
+                );
+
+                ui.add_space(5.0);
+                ui.label("Debug View:");
+                ui.radio_value(&mut state.bloom_debug_mode, 0, "Off (Normal)");
+                ui.radio_value(&mut state.bloom_debug_mode, 1, "Bright Pass");
+                ui.radio_value(&mut state.bloom_debug_mode, 2, "Blurred Bloom");
                 ui.add(egui::Slider::new(&mut state.bloom_strength, 0.0..=3.0).text("Strength"));
+
+                ui.horizontal(|ui| {
+                    ui.label("Method:");
+                    ui.radio_value(&mut state.bloom_method, BloomMethod::MipChain, "Mip Chain");
+                    ui.radio_value(&mut state.bloom_method, BloomMethod::Gaussian, "Gaussian");
+                });
+                if state.bloom_method == BloomMethod::Gaussian {
+                    ui.add(
+                        egui::Slider::new(&mut state.bloom_blur_iterations, 1..=20)
+                            .text("Blur Iterations"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut state.bloom_blur_sigma, 0.5..=5.0)
+                            .text("Blur Sigma"),
+                    );
+                }
             }
 
             ui.add_space(10.0);
 
+            // Tone mapping and lens controls (applied whether or not bloom is enabled)
+            ui.heading("Tone Mapping");
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.tonemap, ToneMapping::Reinhard, "Reinhard");
+                ui.radio_value(&mut state.tonemap, ToneMapping::Aces, "ACES");
+                ui.radio_value(&mut state.tonemap, ToneMapping::Uncharted2, "Uncharted 2");
+            });
+            ui.add(egui::Slider::new(&mut state.exposure, 0.1..=5.0).text("Exposure"));
+            ui.add(egui::Slider::new(&mut state.vignette, 0.0..=1.0).text("Vignette"));
+            ui.checkbox(
+                &mut state.gamma_correction,
+                "Gamma Correction (sRGB output)",
+            );
+            ui.add_enabled(
+                state.gamma_correction && state.srgb_framebuffer_supported,
+                egui::Checkbox::new(&mut state.srgb_framebuffer, "Hardware sRGB Encoding"),
+            )
+            .on_disabled_hover_text("Needs gamma correction and an sRGB-capable window");
+            ui.add(
+                egui::Slider::new(&mut state.chromatic_aberration, 0.0..=0.02)
+                    .text("Chromatic Aberration"),
+            );
+
+            ui.add_space(10.0);
+
             // God ray controls
             ui.heading("God Rays");
             ui.separator();
@@ -737,12 +1456,51 @@ fn render_ui(
             ui.add(egui::Slider::new(&mut state.godray_strength, 0.0..=2.0).text("Strength"));
             ui.add(egui::Slider::new(&mut state.godray_exposure, 0.0..=2.0).text("Exposure"));
             ui.add(egui::Slider::new(&mut state.godray_decay, 0.8..=1.0).text("Decay"));
+            ui.add(egui::Slider::new(&mut state.godray_density, 0.1..=1.5).text("Density"));
+            ui.add(egui::Slider::new(&mut state.godray_weight, 0.05..=1.0).text("Weight"));
+            ui.add(egui::Slider::new(&mut state.godray_samples, 8..=200).text("Samples"));
+            ui.checkbox(&mut state.godray_dither, "Dither Samples");
+            ui.add(egui::Slider::new(&mut state.godray_max_radius, 0.1..=2.0).text("Max Radius"));
+            ui.add(egui::Slider::new(&mut state.godray_feather, 0.0..=1.0).text("Feather"));
+            ui.horizontal(|ui| {
+                ui.label("Tint:");
+                ui.color_edit_button_rgb(&mut state.godray_tint);
+            });
+            ui.add(
+                egui::Slider::new(&mut state.godray_resolution_scale, 0.25..=1.0)
+                    .text("Resolution Scale"),
+            );
+            ui.checkbox(
+                &mut state.godray_depth_occlusion,
+                "Reuse Scene Depth for Occlusion",
+            );
 
             ui.add_space(5.0);
             ui.label("Debug Mode:");
             ui.radio_value(&mut state.godray_debug_mode, 0, "Off (Normal)");
             ui.radio_value(&mut state.godray_debug_mode, 1, "Occlusion Buffer");
             ui.radio_value(&mut state.godray_debug_mode, 2, "Radial Blur");
+            ui.radio_value(&mut state.godray_debug_mode, 3, "Rays Only");
+
+            ui.add_space(10.0);
+
+            ui.checkbox(
+                &mut state.screenshot_include_ui,
+                "Include UI in Screenshots",
+            );
+
+            // Persist the current look for the next run
+            if ui.button("Save Settings").clicked() {
+                state.save_settings_requested = true;
+            }
+            ui.label(format!("Saved to {}", CONFIG_PATH));
+
+            if ui.button("Reload Shaders").clicked() {
+                state.reload_shaders_requested = true;
+            }
+            for error in &state.shader_errors {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
 
             ui.add_space(10.0);
 
@@ -752,6 +1510,16 @@ fn render_ui(
             ui.label("WASD - Move camera");
             ui.label("QE - Move up/down");
             ui.label("Arrows - Look around");
+            ui.label("Right mouse (hold) - Mouse look");
+            ui.label("Scroll - Zoom");
+            ui.label("Left click - Select object");
+            ui.label("F - Toggle flashlight");
+            ui.label("F2 - Screenshot");
+            ui.label("F12 - Capture offscreen composite");
+            ui.label("F5 - Reload shaders");
+            ui.label("F11 - Toggle fullscreen");
+            ui.label("Space - Pause animation");
+            ui.label(". - Step one frame");
             ui.label("ESC - Quit");
         });
 
@@ -765,6 +1533,21 @@ fn render_ui(
 
             let mut changed = false;
 
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .radio_value(&mut state.terrain_noise_type, NoiseType::Fbm, "fBm (hills)")
+                    .changed();
+                changed |= ui
+                    .radio_value(
+                        &mut state.terrain_noise_type,
+                        NoiseType::Ridged,
+                        "Ridged (mountains)",
+                    )
+                    .changed();
+            });
+
+            ui.add_space(5.0);
+
             changed |= ui
                 .add(egui::Slider::new(&mut state.terrain_octaves, 1..=8).text("Octaves"))
                 .changed();
@@ -807,6 +1590,43 @@ fn render_ui(
                 .changed();
             ui.label("Vertical exaggeration");
 
+            ui.add_space(5.0);
+
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut state.terrain_warp_strength, 0.0..=4.0)
+                        .text("Warp Strength"),
+                )
+                .changed();
+            ui.label("Domain warp (0 = off, 1-2 = swirly, higher = folded)");
+
+            ui.add_space(10.0);
+
+            // Colors only rebuild the mesh, the height field is kept
+            ui.heading("Surface");
+            ui.separator();
+            let mut recolor = false;
+            ui.horizontal(|ui| {
+                recolor |= ui
+                    .color_edit_button_rgb(&mut state.terrain_grass_color)
+                    .changed();
+                ui.label("Grass");
+                recolor |= ui
+                    .color_edit_button_rgb(&mut state.terrain_rock_color)
+                    .changed();
+                ui.label("Rock");
+            });
+            recolor |= ui
+                .add(
+                    egui::Slider::new(&mut state.terrain_slope_threshold, 0.0..=1.0)
+                        .text("Rock Slope"),
+                )
+                .changed();
+            ui.label("Steepness where grass turns to rock (0.3 = about 45 degrees)");
+            if recolor {
+                state.terrain_recolor_requested = true;
+            }
+
             ui.add_space(10.0);
 
             if changed {
@@ -832,6 +1652,11 @@ fn render_ui(
                 state.terrain_lacunarity = 2.0;
                 state.terrain_noise_scale = 0.15;
                 state.terrain_height_scale = 10.0;
+                state.terrain_noise_type = NoiseType::Fbm;
+                state.terrain_warp_strength = 0.0;
+                state.terrain_grass_color = [0.3, 0.55, 0.2];
+                state.terrain_rock_color = [0.45, 0.42, 0.4];
+                state.terrain_slope_threshold = 0.3;
                 state.terrain_needs_regeneration = true;
             }
         });
@@ -839,7 +1664,7 @@ fn render_ui(
 
 fn render_performance_ui(
     egui_ctx: &egui::Context,
-    perf_monitor: &PerformanceMonitor,
+    perf_monitor: &mut PerformanceMonitor,
     cpu_delta_time: f32,
 ) {
     // Performance monitoring window
@@ -861,6 +1686,34 @@ fn render_performance_ui(
             let total_gpu_time = perf_monitor.get_total_time_ms();
             ui.label(format!("Total GPU Time: {:.3} ms", total_gpu_time));
 
+            // Whole-frame GPU vs CPU time tells which side is the bottleneck
+            ui.label(format!(
+                "CPU Render Time: {:.3} ms",
+                perf_monitor.get_frame_cpu_ms()
+            ));
+            if let Some(ratio) = perf_monitor.get_gpu_cpu_ratio() {
+                let bound = if ratio > 1.0 {
+                    "GPU-bound"
+                } else {
+                    "CPU-bound"
+                };
+                ui.label(format!("GPU/CPU Ratio: {:.2} ({})", ratio, bound));
+            }
+            for (name, value) in perf_monitor.get_frame_stats() {
+                ui.label(format!("{}: {}", name, value));
+            }
+
+            // Counters that went over their time budget this frame
+            for (name, last_ms, budget_ms) in perf_monitor.get_over_budget() {
+                ui.label(
+                    RichText::new(format!(
+                        "Over budget: {} ({:.2} / {:.2} ms)",
+                        name, last_ms, budget_ms
+                    ))
+                    .color(egui::Color32::from_rgb(255, 100, 100)),
+                );
+            }
+
             ui.add_space(10.0);
             ui.heading("GPU Render Passes");
             ui.separator();
@@ -882,6 +1735,8 @@ fn render_performance_ui(
                         ui.label(RichText::new("Pass Name").strong());
                         ui.label(RichText::new("Last (ms)").strong());
                         ui.label(RichText::new("Avg (ms)").strong());
+                        ui.label(RichText::new("P99 (ms)").strong());
+                        ui.label(RichText::new("Max (ms)").strong());
                         ui.label(RichText::new("% of Total").strong());
                         ui.end_row();
 
@@ -905,6 +1760,10 @@ fn render_performance_ui(
                             ui.label(RichText::new(&name).color(color));
                             ui.monospace(format!("{:>6.3}", last_ms));
                             ui.monospace(format!("{:>6.3}", avg_ms));
+                            let p99_ms = perf_monitor.get_percentile_ms(&name, 99.0).unwrap_or(0.0);
+                            let max_ms = perf_monitor.get_max_ms(&name).unwrap_or(0.0);
+                            ui.monospace(format!("{:>6.3}", p99_ms));
+                            ui.monospace(format!("{:>6.3}", max_ms));
                             ui.monospace(format!("{:>5.1}%", percentage));
                             ui.end_row();
                         }
@@ -913,8 +1772,114 @@ fn render_performance_ui(
                 ui.add_space(10.0);
                 ui.separator();
                 ui.monospace(format!("Total: {:.3} ms", total_gpu_time));
+                ui.horizontal(|ui| {
+                    if !perf_monitor.all_warmed_up() {
+                        ui.label(
+                            RichText::new("Warming up - averages still settling")
+                                .color(egui::Color32::from_rgb(255, 200, 100)),
+                        );
+                    }
+                    if ui.button("Reset Counters").clicked() {
+                        perf_monitor.reset_counters();
+                    }
+                });
+
+                ui.add_space(10.0);
+                ui.heading("GPU Timing History");
+                ui.separator();
+                render_performance_graph(ui, perf_monitor);
             }
 
+            ui.add_space(10.0);
+            ui.heading("Frame Time Distribution");
+            ui.separator();
+            let histogram = perf_monitor.get_frame_histogram();
+            let total_frames: u64 = histogram.iter().map(|(_, count)| count).sum();
+            for (label, count) in histogram {
+                let fraction = if total_frames > 0 {
+                    count as f32 / total_frames as f32
+                } else {
+                    0.0
+                };
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{:>8}", label));
+                    ui.add(
+                        egui::ProgressBar::new(fraction)
+                            .desired_width(200.0)
+                            .text(format!("{} ({:.1}%)", count, fraction * 100.0)),
+                    );
+                });
+            }
+            if ui.button("Reset Histogram").clicked() {
+                perf_monitor.reset_histogram();
+            }
+
+            ui.add_space(10.0);
+            ui.heading("GPU Memory");
+            ui.separator();
+            const MB: f32 = 1024.0 * 1024.0;
+            match gpu_memory::query_vram() {
+                Some(vram) => {
+                    let available_mb = vram.available_kb as f32 / 1024.0;
+                    match vram.total_kb {
+                        Some(total_kb) => {
+                            let total_mb = total_kb as f32 / 1024.0;
+                            ui.label(format!(
+                                "VRAM: {:.0} / {:.0} MB used ({:.0} MB free)",
+                                total_mb - available_mb,
+                                total_mb,
+                                available_mb
+                            ));
+                        }
+                        None => {
+                            ui.label(format!("VRAM: {:.0} MB free", available_mb));
+                        }
+                    }
+                }
+                None => {
+                    ui.label("VRAM: not reported by driver");
+                }
+            }
+            ui.label(format!(
+                "Meshes: {:.2} MB",
+                gpu_memory::allocated_bytes(GpuResource::Mesh) as f32 / MB
+            ));
+            ui.label(format!(
+                "Textures: {:.2} MB",
+                gpu_memory::allocated_bytes(GpuResource::Texture) as f32 / MB
+            ));
+            ui.label(format!(
+                "Framebuffers: {:.2} MB",
+                gpu_memory::allocated_bytes(GpuResource::Framebuffer) as f32 / MB
+            ));
+            ui.monospace(format!(
+                "Tracked total: {:.2} MB",
+                gpu_memory::total_allocated_bytes() as f32 / MB
+            ));
+
+            ui.add_space(10.0);
+            ui.heading("Recording");
+            ui.separator();
+            ui.horizontal(|ui| {
+                if perf_monitor.is_recording() {
+                    if ui.button("Stop Recording").clicked() {
+                        perf_monitor.stop_recording();
+                    }
+                } else if ui.button("Start Recording").clicked() {
+                    perf_monitor.start_recording();
+                }
+                if ui.button("Export CSV").clicked() {
+                    match perf_monitor.export_csv(PERFORMANCE_CSV_PATH) {
+                        Ok(()) => println!("Performance data written to {}", PERFORMANCE_CSV_PATH),
+                        Err(e) => eprintln!("Failed to write {}: {}", PERFORMANCE_CSV_PATH, e),
+                    }
+                }
+            });
+            ui.label(format!(
+                "Frames captured: {}",
+                perf_monitor.recorded_frame_count()
+            ));
+
             ui.add_space(10.0);
             ui.label("Monitor: ON");
             ui.label(
@@ -924,3 +1889,68 @@ fn render_performance_ui(
             );
         });
 }
+
+/// Plot every counter's history as a line graph (oldest on the left, newest on the right)
+fn render_performance_graph(ui: &mut egui::Ui, perf_monitor: &PerformanceMonitor) {
+    // Distinct colors cycled through per counter
+    const PALETTE: [egui::Color32; 6] = [
+        egui::Color32::from_rgb(100, 200, 255),
+        egui::Color32::from_rgb(255, 150, 80),
+        egui::Color32::from_rgb(120, 255, 120),
+        egui::Color32::from_rgb(255, 100, 200),
+        egui::Color32::from_rgb(255, 230, 90),
+        egui::Color32::from_rgb(180, 140, 255),
+    ];
+
+    let series: Vec<(String, Vec<f32>)> = perf_monitor
+        .get_all_counters()
+        .into_iter()
+        .filter_map(|(name, _, _)| {
+            perf_monitor
+                .get_history_ms(&name)
+                .map(|history| (name, history))
+        })
+        .collect();
+
+    // Scale the graph to the slowest sample so spikes stay visible
+    let max_ms = series
+        .iter()
+        .flat_map(|(_, history)| history.iter().copied())
+        .fold(0.0f32, f32::max)
+        .max(0.001);
+
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), 120.0),
+        egui::Sense::hover(),
+    );
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+    for (i, (_, history)) in series.iter().enumerate() {
+        if history.len() < 2 {
+            continue;
+        }
+        let step = rect.width() / (history.len() - 1) as f32;
+        let points: Vec<egui::Pos2> = history
+            .iter()
+            .enumerate()
+            .map(|(x, ms)| {
+                egui::pos2(
+                    rect.left() + x as f32 * step,
+                    rect.bottom() - (ms / max_ms) * rect.height(),
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.5, PALETTE[i % PALETTE.len()]),
+        ));
+    }
+
+    ui.monospace(format!("Scale: 0 - {:.3} ms", max_ms));
+
+    // Legend
+    for (i, (name, _)) in series.iter().enumerate() {
+        ui.label(RichText::new(name).color(PALETTE[i % PALETTE.len()]));
+    }
+}
@@ -14,10 +14,15 @@ pub struct Material {
 
     /// Shininess - controls how focused the specular highlight is (higher = sharper)
     pub shininess: f32,
+
+    /// Emissive color - light the surface gives off by itself, added after lighting
+    /// Values above 1.0 make it bright enough for the bloom bright pass to pick up
+    pub emissive: glm::Vec3,
 }
 
 impl Material {
     /// Creates a new material with specified properties
+    #[allow(dead_code)]
     pub fn new(
         ambient: glm::Vec3,
         diffuse: glm::Vec3,
@@ -29,6 +34,7 @@ impl Material {
             diffuse,
             specular,
             shininess,
+            emissive: glm::vec3(0.0, 0.0, 0.0),
         }
     }
 
@@ -39,6 +45,7 @@ impl Material {
             diffuse: color,                     // Main color
             specular: glm::vec3(0.5, 0.5, 0.5), // White-ish highlights
             shininess: 32.0,                    // Medium shine
+            emissive: glm::vec3(0.0, 0.0, 0.0),
         }
     }
 
@@ -49,6 +56,7 @@ impl Material {
             diffuse: color * 0.8, // Slightly darker main color
             specular: color,      // Colored highlights (metals reflect their color)
             shininess: 64.0,      // High shine
+            emissive: glm::vec3(0.0, 0.0, 0.0),
         }
     }
 
@@ -59,6 +67,7 @@ impl Material {
             diffuse: color,
             specular: glm::vec3(0.1, 0.1, 0.1), // Very dim highlights
             shininess: 8.0,                     // Low shine (rough surface)
+            emissive: glm::vec3(0.0, 0.0, 0.0),
         }
     }
 
@@ -69,6 +78,29 @@ impl Material {
             diffuse: color,
             specular: glm::vec3(0.3, 0.3, 0.3),
             shininess: 4.0, // Very low shine
+            emissive: glm::vec3(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Creates a self-lit material that glows `color` at `strength` regardless of the lights
+    /// (strength above 1.0 makes it bloom)
+    pub fn emissive(color: glm::Vec3, strength: f32) -> Self {
+        Material {
+            ambient: glm::vec3(0.0, 0.0, 0.0),
+            diffuse: color * 0.2, // A little shading so the shape still reads
+            specular: glm::vec3(0.0, 0.0, 0.0),
+            shininess: 1.0,
+            emissive: color * strength,
+        }
+    }
+
+    /// The color this material appears to give off, e.g. for god ray orbs:
+    /// the emissive color when it has one, otherwise the diffuse color
+    pub fn glow_color(&self) -> glm::Vec3 {
+        if self.emissive != glm::Vec3::zeros() {
+            self.emissive
+        } else {
+            self.diffuse
         }
     }
 
@@ -79,6 +111,7 @@ impl Material {
             diffuse: glm::vec3(0.4, 0.4, 0.4),
             specular: glm::vec3(0.77, 0.77, 0.77),
             shininess: 128.0, // Very high shine
+            emissive: glm::vec3(0.0, 0.0, 0.0),
         }
     }
 }
@@ -1,7 +1,10 @@
+use crate::gpu_memory::{self, GpuResource};
+use nalgebra_glm as glm;
+use std::collections::HashMap;
 use std::mem;
 use std::ptr;
 
-/// Represents a single vertex with position, color, normal, and UV coordinates
+/// Represents a single vertex with position, color, normal, UV coordinates and tangent
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
@@ -9,16 +12,106 @@ pub struct Vertex {
     pub color: [f32; 3],    // r, g, b
     pub normal: [f32; 3],   // nx, ny, nz
     pub uv: [f32; 2],       // u, v (texture coordinates)
+    pub tangent: [f32; 3],  // Direction of increasing u, for normal mapping
 }
 
 impl Vertex {
     /// Creates a new vertex with position, color, normal, and UV coordinates
+    /// The tangent starts at zero and is filled in from the UVs when the mesh is built
     pub fn new(position: [f32; 3], color: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> Self {
         Vertex {
             position,
             color,
             normal,
             uv,
+            tangent: [0.0; 3],
+        }
+    }
+}
+
+/// Describes interleaved float vertex data: the component count of each attribute,
+/// in shader location order
+#[derive(Clone, Debug)]
+pub struct VertexLayout {
+    attributes: Vec<usize>,
+    stride: usize, // Floats per vertex
+}
+
+#[allow(dead_code)]
+impl VertexLayout {
+    /// Panics on an empty layout or an attribute size outside 1..=4, since
+    /// `glVertexAttribPointer` only takes 1 to 4 components and a zero stride
+    /// can't be divided into vertices
+    pub fn new(attribute_sizes: &[usize]) -> Self {
+        assert!(
+            !attribute_sizes.is_empty(),
+            "Vertex layout needs at least one attribute"
+        );
+        assert!(
+            attribute_sizes.iter().all(|size| (1..=4).contains(size)),
+            "Vertex attribute sizes must be 1 to 4 components, got {:?}",
+            attribute_sizes
+        );
+        VertexLayout {
+            attributes: attribute_sizes.to_vec(),
+            stride: attribute_sizes.iter().sum(),
+        }
+    }
+
+    /// The `Vertex` layout: position (3), color (3), normal (3), UV (2), tangent (3)
+    pub fn standard() -> Self {
+        Self::new(&[3, 3, 3, 2, 3])
+    }
+
+    /// Position (3) and color (3) only, e.g. for particles and debug lines
+    pub fn position_color() -> Self {
+        Self::new(&[3, 3])
+    }
+
+    /// Component count of each attribute
+    pub fn attributes(&self) -> &[usize] {
+        &self.attributes
+    }
+
+    /// Floats per vertex
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+/// First attribute location of the per-instance model matrix (one vec4 column per
+/// location, so it occupies 5-8, after the standard layout). Must match basic.vert
+const INSTANCE_MATRIX_LOCATION: u32 = 5;
+
+/// Element type of a mesh's index buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexType {
+    U16, // Used whenever every index fits, halving the index buffer size
+    U32,
+}
+
+impl IndexType {
+    /// Smallest type that can index `vertex_count` vertices
+    fn for_vertex_count(vertex_count: usize) -> Self {
+        if vertex_count <= u16::MAX as usize + 1 {
+            IndexType::U16
+        } else {
+            IndexType::U32
+        }
+    }
+
+    fn gl_type(self) -> u32 {
+        match self {
+            IndexType::U16 => gl::UNSIGNED_SHORT,
+            IndexType::U32 => gl::UNSIGNED_INT,
+        }
+    }
+
+    /// Bytes per index
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U16 => mem::size_of::<u16>(),
+            IndexType::U32 => mem::size_of::<u32>(),
         }
     }
 }
@@ -28,8 +121,21 @@ pub struct Mesh {
     vao: u32,
     vbo: u32,
     ebo: Option<u32>,
+    index_type: IndexType,
     vertex_count: i32,
     index_count: i32,
+    instance_vbo: Option<u32>, // Per-instance model matrices for draw_instanced
+    instance_count: i32,
+    attribute_count: usize, // Per-vertex attributes, at locations 0..attribute_count
+    gpu_bytes: usize,       // VBO + EBO (+ instance VBO) size, reported to the GPU memory tally
+    // Local-space bounding box, computed once from the vertex positions
+    bounds_min: glm::Vec3,
+    bounds_max: glm::Vec3,
+    // CPU copy of the geometry (empty for GPU-only meshes). Positions are kept for
+    // every layout; full vertices only for the standard one
+    vertices: Option<Vec<Vertex>>,
+    positions: Vec<glm::Vec3>,
+    indices: Vec<u32>, // Empty for non-indexed meshes
 }
 
 #[allow(dead_code)]
@@ -380,6 +486,96 @@ impl Mesh {
         Mesh::new_indexed(&vertices, &indices)
     }
 
+    /// Creates a capsule mesh (a cylinder with hemispherical caps) using indexed rendering
+    /// The caps and body share the ring vertices at the seams, so there are no cracks,
+    /// and the seam normals point straight out for both the caps and the body.
+    ///
+    /// # Arguments
+    /// * `radius` - Radius of the body and caps
+    /// * `cylinder_height` - Height of the straight section (total height adds 2 * radius)
+    /// * `segments` - Number of divisions around the circumference
+    /// * `rings` - Number of latitude divisions in each cap
+    /// * `color` - RGB color for all vertices
+    pub fn capsule(
+        radius: f32,
+        cylinder_height: f32,
+        segments: u32,
+        rings: u32,
+        color: [f32; 3],
+    ) -> Self {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        let half_height = cylinder_height / 2.0;
+        // Profile length (cap arc + body + cap arc), used to spread V evenly
+        let profile_length = std::f32::consts::PI * radius + cylinder_height;
+
+        // Generate vertices: the top cap's rings (pole to equator), then the bottom cap's
+        // (equator to pole). The body is the band between the two equator rings.
+        for ring in 0..=(2 * rings + 1) {
+            let (theta, y_offset) = if ring <= rings {
+                (
+                    ring as f32 * std::f32::consts::FRAC_PI_2 / rings as f32,
+                    half_height,
+                )
+            } else {
+                (
+                    std::f32::consts::FRAC_PI_2
+                        + (ring - rings - 1) as f32 * std::f32::consts::FRAC_PI_2 / rings as f32,
+                    -half_height,
+                )
+            };
+            let sin_theta = theta.sin();
+            let cos_theta = theta.cos();
+
+            // Distance along the profile from the top pole
+            let arc = theta * radius + if ring > rings { cylinder_height } else { 0.0 };
+            let v = arc / profile_length;
+
+            for seg in 0..=segments {
+                let phi = seg as f32 * 2.0 * std::f32::consts::PI / segments as f32;
+                let sin_phi = phi.sin();
+                let cos_phi = phi.cos();
+
+                // Same as the sphere, with each cap shifted to its end of the body
+                let x = cos_phi * sin_theta;
+                let y = cos_theta;
+                let z = sin_phi * sin_theta;
+
+                let position = [x * radius, y * radius + y_offset, z * radius];
+                let normal = [x, y, z];
+                let u = seg as f32 / segments as f32;
+
+                vertices.push(Vertex::new(position, color, normal, [u, v]));
+            }
+        }
+
+        // Generate indices (caps and body stitched the same way as sphere rings)
+        for ring in 0..(2 * rings + 1) {
+            for seg in 0..segments {
+                let current_ring_start = ring * (segments + 1);
+                let next_ring_start = (ring + 1) * (segments + 1);
+
+                let current = current_ring_start + seg;
+                let next = current_ring_start + seg + 1;
+                let current_below = next_ring_start + seg;
+                let next_below = next_ring_start + seg + 1;
+
+                // First triangle
+                indices.push(current);
+                indices.push(current_below);
+                indices.push(next);
+
+                // Second triangle
+                indices.push(next);
+                indices.push(current_below);
+                indices.push(next_below);
+            }
+        }
+
+        Mesh::new_indexed(&vertices, &indices)
+    }
+
     /// Creates a torus mesh using indexed rendering
     ///
     /// # Arguments
@@ -797,6 +993,194 @@ impl Mesh {
         Mesh::new(&vertices)
     }
 
+    /// Load a Wavefront .obj model (v, vt, vn and f lines; everything else is ignored)
+    /// Faces with more than three vertices are fan-triangulated. Missing UVs default to
+    /// [0, 0], and missing normals are computed by averaging the face normals at each position.
+    pub fn from_obj(path: &str) -> Result<Mesh, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read OBJ file {}: {}", path, e))?;
+
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+
+        // Each unique (position, uv, normal) index triple becomes one vertex
+        let mut vertex_lookup: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+        let mut vertex_keys: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+
+        for (line_number, line) in source.lines().enumerate() {
+            let error = |msg: &str| format!("{}:{}: {}", path, line_number + 1, msg);
+            let mut tokens = line.split_whitespace();
+
+            match tokens.next() {
+                Some("v") => {
+                    positions.push(parse_floats::<3>(tokens).ok_or_else(|| error("bad vertex"))?)
+                }
+                Some("vt") => {
+                    // The optional third (w) component is ignored
+                    let uv =
+                        parse_floats::<2>(tokens).ok_or_else(|| error("bad texture coordinate"))?;
+                    uvs.push(uv);
+                }
+                Some("vn") => {
+                    normals.push(parse_floats::<3>(tokens).ok_or_else(|| error("bad normal"))?)
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for token in tokens {
+                        let key =
+                            parse_face_vertex(token, positions.len(), uvs.len(), normals.len())
+                                .ok_or_else(|| error(&format!("bad face vertex '{}'", token)))?;
+                        let index = *vertex_lookup.entry(key).or_insert_with(|| {
+                            vertex_keys.push(key);
+                            (vertex_keys.len() - 1) as u32
+                        });
+                        face.push(index);
+                    }
+                    if face.len() < 3 {
+                        return Err(error("face has fewer than 3 vertices"));
+                    }
+                    // Fan triangulation (fine for the convex polygons exporters produce)
+                    for i in 1..face.len() - 1 {
+                        indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if indices.is_empty() {
+            return Err(format!("OBJ file {} contains no faces", path));
+        }
+
+        let missing_normals = vertex_keys.iter().any(|(_, _, n)| n.is_none());
+        let mut vertices: Vec<Vertex> = vertex_keys
+            .iter()
+            .map(|&(p, t, n)| {
+                Vertex::new(
+                    positions[p],
+                    [1.0, 1.0, 1.0],
+                    n.map_or([0.0, 0.0, 0.0], |n| normals[n]),
+                    t.map_or([0.0, 0.0], |t| uvs[t]),
+                )
+            })
+            .collect();
+
+        if missing_normals {
+            // Average face normals per position (not per vertex), so seams where only the
+            // UVs differ still shade smoothly. Cross products are area weighted.
+            let mut accumulated = vec![glm::vec3(0.0, 0.0, 0.0); positions.len()];
+            for triangle in indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| vertex_keys[triangle[i] as usize].0);
+                let [pa, pb, pc] = [a, b, c].map(|i| glm::Vec3::from(positions[i]));
+                let face_normal = glm::cross(&(pb - pa), &(pc - pa));
+                for i in [a, b, c] {
+                    accumulated[i] += face_normal;
+                }
+            }
+            for (vertex, &(p, _, n)) in vertices.iter_mut().zip(&vertex_keys) {
+                if n.is_some() {
+                    continue; // Keep normals the file provides
+                }
+                let normal = accumulated[p];
+                if glm::length(&normal) > f32::EPSILON {
+                    vertex.normal = glm::normalize(&normal).into();
+                }
+            }
+        }
+
+        Ok(Mesh::new_indexed(&vertices, &indices))
+    }
+
+    /// Replace the normals of an indexed triangle list with smooth per-vertex normals
+    /// Each triangle's face normal is added to its three vertices weighted by the
+    /// triangle's area (the unnormalized cross product), then the sums are normalized.
+    /// Degenerate (zero-area) triangles are skipped, and vertices that end up with no
+    /// usable direction keep their previous normal instead of becoming NaN or zero.
+    /// Call before uploading with `new_indexed`.
+    pub fn recompute_smooth_normals(vertices: &mut [Vertex], indices: &[u32]) {
+        let mut accumulated = vec![glm::vec3(0.0, 0.0, 0.0); vertices.len()];
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let [pa, pb, pc] = [a, b, c].map(|i| glm::Vec3::from(vertices[i].position));
+            let face_normal = glm::cross(&(pb - pa), &(pc - pa));
+
+            // Zero-area triangles (repeated or collinear points) have no direction
+            let area = glm::length(&face_normal);
+            if area <= f32::EPSILON || !area.is_finite() {
+                continue;
+            }
+
+            for i in [a, b, c] {
+                accumulated[i] += face_normal;
+            }
+        }
+
+        for (vertex, normal) in vertices.iter_mut().zip(accumulated) {
+            // Opposing faces can also cancel out exactly (e.g. a double-sided sheet)
+            if glm::length(&normal) > f32::EPSILON {
+                vertex.normal = glm::normalize(&normal).into();
+            }
+        }
+    }
+
+    /// Set each vertex's tangent to the direction its UVs' u increases along the surface
+    /// Triangles come from `indices`, or from consecutive vertices when there are none.
+    /// Each triangle's tangent is added to its vertices, then the sums are made
+    /// perpendicular to the normal and normalized. Vertices without usable UVs get an
+    /// arbitrary tangent perpendicular to the normal so the TBN basis stays valid.
+    /// The bitangent is rebuilt as cross(normal, tangent), so mirrored UVs come out flipped.
+    pub fn compute_tangents(vertices: &mut [Vertex], indices: Option<&[u32]>) {
+        let sequential: Vec<u32>;
+        let indices = match indices {
+            Some(indices) => indices,
+            None => {
+                sequential = (0..vertices.len() as u32).collect();
+                &sequential
+            }
+        };
+
+        let mut accumulated = vec![glm::vec3(0.0, 0.0, 0.0); vertices.len()];
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let [pa, pb, pc] = [a, b, c].map(|i| glm::Vec3::from(vertices[i].position));
+            let [ta, tb, tc] = [a, b, c].map(|i| glm::Vec2::from(vertices[i].uv));
+
+            let (edge1, edge2) = (pb - pa, pc - pa);
+            let (duv1, duv2) = (tb - ta, tc - ta);
+            // Zero when the UVs are degenerate (e.g. all three at a sphere's pole)
+            let det = duv1.x * duv2.y - duv2.x * duv1.y;
+            if det.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+            for i in [a, b, c] {
+                accumulated[i] += tangent;
+            }
+        }
+
+        for (vertex, tangent) in vertices.iter_mut().zip(accumulated) {
+            let normal = glm::Vec3::from(vertex.normal);
+            // Gram-Schmidt: remove the part along the normal
+            let mut tangent = tangent - normal * glm::dot(&normal, &tangent);
+            if glm::length(&tangent) <= f32::EPSILON {
+                // Any perpendicular will do; pick an axis that isn't parallel to the normal
+                let axis = if normal.x.abs() < 0.9 {
+                    glm::vec3(1.0, 0.0, 0.0)
+                } else {
+                    glm::vec3(0.0, 1.0, 0.0)
+                };
+                tangent = glm::cross(&axis, &normal);
+            }
+            if glm::length(&tangent) > f32::EPSILON {
+                vertex.tangent = glm::normalize(&tangent).into();
+            }
+        }
+    }
+
     pub fn new(vertices: &[Vertex]) -> Self {
         Self::new_internal(vertices, None)
     }
@@ -806,10 +1190,45 @@ impl Mesh {
     }
 
     pub fn new_internal(vertices: &[Vertex], indices: Option<&[u32]>) -> Self {
+        // Fill in tangents unless the caller already provided them
+        let mut vertices = vertices.to_vec();
+        if vertices.iter().all(|vertex| vertex.tangent == [0.0; 3]) {
+            Self::compute_tangents(&mut vertices, indices);
+        }
+
+        // Vertex is #[repr(C)] and made only of f32 arrays, so it can be viewed as a flat
+        // float slice laid out exactly as VertexLayout::standard() describes
+        let data = unsafe {
+            std::slice::from_raw_parts(
+                vertices.as_ptr() as *const f32,
+                mem::size_of_val(vertices.as_slice()) / mem::size_of::<f32>(),
+            )
+        };
+        let mut mesh = Self::from_raw(data, &VertexLayout::standard(), indices);
+        mesh.vertices = Some(vertices);
+        mesh
+    }
+
+    /// Like `new`/`new_indexed`, but without keeping a CPU copy of the geometry
+    /// Saves memory for large static meshes; `vertices()` and `indices()` return
+    /// nothing and `raycast` falls back to the bounding box
+    pub fn new_gpu_only(vertices: &[Vertex], indices: Option<&[u32]>) -> Self {
+        let mut mesh = Self::new_internal(vertices, indices);
+        mesh.vertices = None;
+        mesh.positions = Vec::new();
+        mesh.indices = Vec::new();
+        mesh
+    }
+
+    /// Create a mesh from interleaved float data described by `layout`
+    /// Attribute i of the layout is bound to shader location i.
+    pub fn from_raw(data: &[f32], layout: &VertexLayout, indices: Option<&[u32]>) -> Self {
         let mut vao = 0;
         let mut vbo = 0;
         let mut ebo = None;
         let index_count;
+        let stride = layout.stride();
+        let index_type = IndexType::for_vertex_count(data.len() / stride);
 
         unsafe {
             // Generate VAO and VBO
@@ -823,66 +1242,51 @@ impl Mesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * mem::size_of::<Vertex>()) as isize,
-                vertices.as_ptr() as *const _,
+                mem::size_of_val(data) as isize,
+                data.as_ptr() as *const _,
                 gl::STATIC_DRAW,
             );
 
-            // Position attribute (location = 0)
-            gl::VertexAttribPointer(
-                0,                               // location
-                3,                               // size (x, y, z)
-                gl::FLOAT,                       // type
-                gl::FALSE,                       // normalized
-                mem::size_of::<Vertex>() as i32, // stride (size of entire Vertex)
-                ptr::null(),                     // offset (0 for position)
-            );
-            gl::EnableVertexAttribArray(0);
-
-            // Color attribute (location = 1)
-            gl::VertexAttribPointer(
-                1,                                                      // location
-                3,                                                      // size (r, g, b)
-                gl::FLOAT,                                              // type
-                gl::FALSE,                                              // normalized
-                mem::size_of::<Vertex>() as i32,                        // stride
-                (3 * mem::size_of::<f32>()) as *const std::ffi::c_void, // offset (3 floats)
-            );
-            gl::EnableVertexAttribArray(1);
-
-            // Normal attribute (location = 2)
-            gl::VertexAttribPointer(
-                2,                                                      // location
-                3,                                                      // size (nx, ny, nz)
-                gl::FLOAT,                                              // type
-                gl::FALSE,                                              // normalized
-                mem::size_of::<Vertex>() as i32,                        // stride
-                (6 * mem::size_of::<f32>()) as *const std::ffi::c_void, // offset (6 floats)
-            );
-            gl::EnableVertexAttribArray(2);
-
-            // UV attribute (location = 3)
-            gl::VertexAttribPointer(
-                3,                                                      // location
-                2,                                                      // size (u, v)
-                gl::FLOAT,                                              // type
-                gl::FALSE,                                              // normalized
-                mem::size_of::<Vertex>() as i32,                        // stride
-                (9 * mem::size_of::<f32>()) as *const std::ffi::c_void, // offset (9 floats: 3 pos + 3 color + 3 normal)
-            );
-            gl::EnableVertexAttribArray(3);
+            // One float attribute per layout entry, packed back to back within a vertex
+            // (standard layout: position, color, normal, UV, tangent at locations 0-4)
+            let mut offset = 0;
+            for (location, &size) in layout.attributes().iter().enumerate() {
+                gl::VertexAttribPointer(
+                    location as u32,                                             // location
+                    size as i32,                                                 // components
+                    gl::FLOAT,                                                   // type
+                    gl::FALSE,                                                   // normalized
+                    (stride * mem::size_of::<f32>()) as i32,                     // stride
+                    (offset * mem::size_of::<f32>()) as *const std::ffi::c_void, // offset
+                );
+                gl::EnableVertexAttribArray(location as u32);
+                offset += size;
+            }
 
             // Handle EBO if indices are provided
             index_count = if let Some(idx) = indices {
                 let mut ebo_id = 0;
                 gl::GenBuffers(1, &mut ebo_id);
                 gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo_id);
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    (idx.len() * mem::size_of::<u32>()) as isize,
-                    idx.as_ptr() as *const _,
-                    gl::STATIC_DRAW,
-                );
+                match index_type {
+                    IndexType::U16 => {
+                        let short_indices: Vec<u16> = idx.iter().map(|&i| i as u16).collect();
+                        gl::BufferData(
+                            gl::ELEMENT_ARRAY_BUFFER,
+                            mem::size_of_val(short_indices.as_slice()) as isize,
+                            short_indices.as_ptr() as *const _,
+                            gl::STATIC_DRAW,
+                        );
+                    }
+                    IndexType::U32 => {
+                        gl::BufferData(
+                            gl::ELEMENT_ARRAY_BUFFER,
+                            mem::size_of_val(idx) as isize,
+                            idx.as_ptr() as *const _,
+                            gl::STATIC_DRAW,
+                        );
+                    }
+                }
                 ebo = Some(ebo_id);
                 idx.len() as i32
             } else {
@@ -894,12 +1298,46 @@ impl Mesh {
             gl::BindVertexArray(0);
         }
 
+        let gpu_bytes = mem::size_of_val(data) + index_count as usize * index_type.size();
+        gpu_memory::track_alloc(GpuResource::Mesh, gpu_bytes);
+
+        // The first attribute is the position (2D positions get z = 0)
+        let position_size = layout.attributes().first().copied().unwrap_or(0).min(3);
+        let mut bounds_min = glm::vec3(f32::MAX, f32::MAX, f32::MAX);
+        let mut bounds_max = glm::vec3(f32::MIN, f32::MIN, f32::MIN);
+        let mut positions = Vec::with_capacity(data.len() / stride);
+        for vertex in data.chunks_exact(stride) {
+            let mut position = glm::vec3(0.0, 0.0, 0.0);
+            for (axis, &value) in vertex[..position_size].iter().enumerate() {
+                position[axis] = value;
+            }
+            bounds_min = glm::min2(&bounds_min, &position);
+            bounds_max = glm::max2(&bounds_max, &position);
+            positions.push(position);
+        }
+        let indices = indices.map(<[u32]>::to_vec).unwrap_or_default();
+        if data.len() < stride {
+            // No vertices - collapse to the origin rather than an inverted box
+            bounds_min = glm::vec3(0.0, 0.0, 0.0);
+            bounds_max = bounds_min;
+        }
+
         Mesh {
             vao,
             vbo,
             ebo,
-            vertex_count: vertices.len() as i32,
+            index_type,
+            vertex_count: (data.len() / stride) as i32,
             index_count,
+            instance_vbo: None,
+            instance_count: 0,
+            attribute_count: layout.attributes().len(),
+            gpu_bytes,
+            bounds_min,
+            bounds_max,
+            vertices: None,
+            positions,
+            indices,
         }
     }
 
@@ -911,7 +1349,7 @@ impl Mesh {
                 gl::DrawElements(
                     gl::TRIANGLES,
                     self.index_count,
-                    gl::UNSIGNED_INT,
+                    self.index_type.gl_type(),
                     ptr::null(),
                 )
             } else {
@@ -921,6 +1359,90 @@ impl Mesh {
         }
     }
 
+    /// Upload one model matrix per instance for `draw_instanced`
+    /// The matrices feed attribute locations 5-8 with a divisor of 1, so this only
+    /// works with vertex layouts of at most `INSTANCE_MATRIX_LOCATION` attributes.
+    /// Calling it again replaces the previous matrices
+    pub fn set_instance_transforms(&mut self, matrices: &[glm::Mat4]) {
+        debug_assert!(
+            self.attribute_count <= INSTANCE_MATRIX_LOCATION as usize,
+            "Instance matrices would overwrite vertex attributes {}..{}",
+            INSTANCE_MATRIX_LOCATION,
+            self.attribute_count
+        );
+        let old_bytes = self.instance_bytes();
+        unsafe {
+            gl::BindVertexArray(self.vao);
+
+            let vbo = *self.instance_vbo.get_or_insert_with(|| {
+                let mut vbo = 0;
+                gl::GenBuffers(1, &mut vbo);
+                vbo
+            });
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                mem::size_of_val(matrices) as isize,
+                matrices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            // A mat4 attribute is four vec4 columns, each at its own location
+            let column_size = mem::size_of::<glm::Vec4>();
+            for column in 0..4 {
+                let location = INSTANCE_MATRIX_LOCATION + column as u32;
+                gl::VertexAttribPointer(
+                    location,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    mem::size_of::<glm::Mat4>() as i32,
+                    (column * column_size) as *const std::ffi::c_void,
+                );
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribDivisor(location, 1);
+            }
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+
+        gpu_memory::track_free(GpuResource::Mesh, old_bytes);
+        let new_bytes = mem::size_of_val(matrices);
+        gpu_memory::track_alloc(GpuResource::Mesh, new_bytes);
+        self.gpu_bytes = self.gpu_bytes - old_bytes + new_bytes;
+        self.instance_count = matrices.len() as i32;
+    }
+
+    /// Draw `count` instances in a single call, using the matrices from
+    /// `set_instance_transforms` (the shader must read the instance matrix attribute)
+    pub fn draw_instanced(&self, count: i32) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            if self.ebo.is_some() {
+                gl::DrawElementsInstanced(
+                    gl::TRIANGLES,
+                    self.index_count,
+                    self.index_type.gl_type(),
+                    ptr::null(),
+                    count,
+                );
+            } else {
+                gl::DrawArraysInstanced(gl::TRIANGLES, 0, self.vertex_count, count);
+            }
+            gl::BindVertexArray(0);
+        }
+    }
+
+    /// Number of matrices uploaded by `set_instance_transforms`
+    pub fn instance_count(&self) -> i32 {
+        self.instance_count
+    }
+
+    fn instance_bytes(&self) -> usize {
+        self.instance_count as usize * mem::size_of::<glm::Mat4>()
+    }
+
     /// Returns the VAO handle (useful for debugging)
     pub fn vao(&self) -> u32 {
         self.vao
@@ -936,6 +1458,86 @@ impl Mesh {
         self.index_count
     }
 
+    /// Element type of the index buffer (None for non-indexed meshes)
+    pub fn index_type(&self) -> Option<IndexType> {
+        self.ebo.map(|_| self.index_type)
+    }
+
+    /// CPU copy of the vertices (None for GPU-only meshes and custom vertex layouts)
+    pub fn vertices(&self) -> Option<&[Vertex]> {
+        self.vertices.as_deref()
+    }
+
+    /// CPU copy of the indices (empty for non-indexed and GPU-only meshes)
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Returns the local-space bounding box as (min, max) corners
+    pub fn bounds(&self) -> (glm::Vec3, glm::Vec3) {
+        (self.bounds_min, self.bounds_max)
+    }
+
+    /// Intersect a ray with the mesh's triangles (both faces), in local space
+    /// Returns the distance to the nearest hit in units of `dir`'s length, so a
+    /// normalized `dir` gives the distance along the ray
+    pub fn raycast(&self, origin: glm::Vec3, dir: glm::Vec3) -> Option<f32> {
+        // Cheap rejection before testing every triangle
+        let box_distance = ray_box_distance(origin, dir, self.bounds_min, self.bounds_max)?;
+        if self.positions.is_empty() {
+            // GPU-only mesh: the bounding box is all we have
+            return Some(box_distance);
+        }
+
+        // Non-indexed meshes store their triangles as consecutive vertices
+        let corner = |i: usize| -> glm::Vec3 {
+            if self.indices.is_empty() {
+                self.positions[i]
+            } else {
+                self.positions[self.indices[i] as usize]
+            }
+        };
+        let corner_count = if self.indices.is_empty() {
+            self.positions.len()
+        } else {
+            self.indices.len()
+        };
+
+        let mut nearest: Option<f32> = None;
+        for first in (0..corner_count - corner_count % 3).step_by(3) {
+            let p0 = corner(first);
+            let p1 = corner(first + 1);
+            let p2 = corner(first + 2);
+
+            // Möller–Trumbore
+            let edge1 = p1 - p0;
+            let edge2 = p2 - p0;
+            let p = dir.cross(&edge2);
+            let det = edge1.dot(&p);
+            if det.abs() < 1e-8 {
+                continue; // Ray parallel to the triangle (or degenerate triangle)
+            }
+            let inv_det = 1.0 / det;
+
+            let to_origin = origin - p0;
+            let u = to_origin.dot(&p) * inv_det;
+            if !(0.0..=1.0).contains(&u) {
+                continue;
+            }
+            let q = to_origin.cross(&edge1);
+            let v = dir.dot(&q) * inv_det;
+            if v < 0.0 || u + v > 1.0 {
+                continue;
+            }
+
+            let t = edge2.dot(&q) * inv_det;
+            if t > 1e-6 && nearest.is_none_or(|nearest| t < nearest) {
+                nearest = Some(t);
+            }
+        }
+        nearest
+    }
+
     /// Returns true if this mesh uses indexed rendering
     pub fn is_indexed(&self) -> bool {
         self.ebo.is_some()
@@ -945,12 +1547,182 @@ impl Mesh {
 // Cleanup when Mesh is dropped
 impl Drop for Mesh {
     fn drop(&mut self) {
+        gpu_memory::track_free(GpuResource::Mesh, self.gpu_bytes);
         unsafe {
             gl::DeleteVertexArrays(1, &self.vao);
             gl::DeleteBuffers(1, &self.vbo);
             if let Some(ebo_id) = self.ebo {
                 gl::DeleteBuffers(1, &ebo_id);
             }
+            if let Some(instance_vbo) = self.instance_vbo {
+                gl::DeleteBuffers(1, &instance_vbo);
+            }
         }
     }
 }
+
+/// Slab test: distance along the ray to an axis-aligned box, or None if it misses
+/// Returns 0.0 when the ray starts inside the box; boxes behind the origin are misses
+pub fn ray_box_distance(
+    origin: glm::Vec3,
+    dir: glm::Vec3,
+    box_min: glm::Vec3,
+    box_max: glm::Vec3,
+) -> Option<f32> {
+    let mut t_near = 0.0f32;
+    let mut t_far = f32::MAX;
+    for axis in 0..3 {
+        if dir[axis].abs() < 1e-8 {
+            // Parallel to this slab: must already be between its planes
+            if origin[axis] < box_min[axis] || origin[axis] > box_max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (box_min[axis] - origin[axis]) / dir[axis];
+        let t2 = (box_max[axis] - origin[axis]) / dir[axis];
+        t_near = t_near.max(t1.min(t2));
+        t_far = t_far.min(t1.max(t2));
+        if t_near > t_far {
+            return None;
+        }
+    }
+    Some(t_near)
+}
+
+/// Parse the first N whitespace-separated floats
+fn parse_floats<'a, const N: usize>(mut tokens: impl Iterator<Item = &'a str>) -> Option<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in &mut values {
+        *value = tokens.next()?.parse().ok()?;
+    }
+    Some(values)
+}
+
+/// Resolve a 1-based (or negative, relative to the end) OBJ index to a 0-based one
+fn resolve_obj_index(token: &str, count: usize) -> Option<usize> {
+    let index: i64 = token.parse().ok()?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    (0..count as i64)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
+
+/// Parse a face vertex in any of the forms `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn parse_face_vertex(
+    token: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Option<(usize, Option<usize>, Option<usize>)> {
+    let mut parts = token.split('/');
+    let position = resolve_obj_index(parts.next()?, position_count)?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(t) => Some(resolve_obj_index(t, uv_count)?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(n) => Some(resolve_obj_index(n, normal_count)?),
+    };
+    Some((position, uv, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(position: [f32; 3]) -> Vertex {
+        Vertex::new(position, [1.0, 1.0, 1.0], [0.0, 0.0, 0.0], [0.0, 0.0])
+    }
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for axis in 0..3 {
+            assert!(
+                (actual[axis] - expected[axis]).abs() < 1e-5,
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn smooth_normals_point_pyramid_apex_up() {
+        // Apex above a square base, four outward-facing sides sharing the apex vertex
+        let mut vertices = vec![
+            vertex([0.0, 1.0, 0.0]),
+            vertex([-1.0, 0.0, 1.0]),
+            vertex([1.0, 0.0, 1.0]),
+            vertex([1.0, 0.0, -1.0]),
+            vertex([-1.0, 0.0, -1.0]),
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1];
+
+        Mesh::recompute_smooth_normals(&mut vertices, &indices);
+
+        assert_close(vertices[0].normal, [0.0, 1.0, 0.0]);
+        for vertex in &vertices[1..] {
+            let normal = glm::Vec3::from(vertex.normal);
+            assert!((glm::length(&normal) - 1.0).abs() < 1e-5);
+            assert!(normal.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn smooth_normals_skip_degenerate_triangles() {
+        let mut vertices = vec![
+            vertex([0.0, 0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0]),
+            vertex([0.0, 0.0, -1.0]),
+            // Only used by zero-area triangles
+            vertex([2.0, 0.0, 0.0]),
+            vertex([3.0, 0.0, 0.0]),
+        ];
+        vertices[3].normal = [0.0, 0.0, 1.0];
+        let indices = [
+            0, 1, 2, // Flat triangle facing up
+            3, 3, 3, // Repeated point
+            1, 3, 4, // Collinear points
+        ];
+
+        Mesh::recompute_smooth_normals(&mut vertices, &indices);
+
+        for vertex in &vertices {
+            assert!(vertex.normal.iter().all(|n| n.is_finite()));
+        }
+        assert_close(vertices[0].normal, [0.0, 1.0, 0.0]);
+        assert_close(vertices[1].normal, [0.0, 1.0, 0.0]);
+        // No usable face: keeps the normal it had
+        assert_close(vertices[3].normal, [0.0, 0.0, 1.0]);
+        assert_close(vertices[4].normal, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn vertex_layout_stride_sums_attributes() {
+        assert_eq!(VertexLayout::standard().stride(), 14);
+        assert_eq!(VertexLayout::position_color().stride(), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn vertex_layout_rejects_empty() {
+        VertexLayout::new(&[]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn vertex_layout_rejects_zero_size() {
+        VertexLayout::new(&[3, 0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn vertex_layout_rejects_oversized_attribute() {
+        VertexLayout::new(&[3, 16]);
+    }
+}
@@ -1,31 +1,38 @@
+/// Permutation table of 0-255 shuffled by `seed`, duplicated to 512 entries so
+/// `table[table[x] + y]` never needs wrapping
+fn shuffled_permutation(seed: u32) -> [u8; 512] {
+    let mut perm = [0u8; 256];
+
+    // Initialize permutations table with values 0-255
+    for i in 0..256 {
+        perm[i] = i as u8;
+    }
+
+    // Shuffle using seed (simple LCG random)
+    let mut rng_state = seed;
+    for i in (1..256).rev() {
+        rng_state = rng_state.wrapping_mul(1664525).wrapping_add(1013904223);
+        let j = (rng_state as usize) % (i + 1);
+        perm.swap(i, j);
+    }
+
+    // Duplicate permutation table to avoid overflow wrapping
+    let mut permutation = [0u8; 512];
+    for i in 0..512 {
+        permutation[i] = perm[i % 256];
+    }
+    permutation
+}
+
 pub struct PerlinNoise {
     permutation: [u8; 512],
 }
 
 impl PerlinNoise {
     pub fn new(seed: u32) -> Self {
-        let mut perm = [0u8; 256];
-
-        // Initialize permutations table with values 0-255
-        for i in 0..256 {
-            perm[i] = i as u8;
-        }
-
-        // Shuffle using seed (simple LCG random)
-        let mut rng_state = seed;
-        for i in (1..256).rev() {
-            rng_state = rng_state.wrapping_mul(1664525).wrapping_add(1013904223);
-            let j = (rng_state as usize) % (i + 1);
-            perm.swap(i, j);
-        }
-
-        // Duplicate permutation table to avoid overflow wrapping
-        let mut permutation = [0u8; 512];
-        for i in 0..512 {
-            permutation[i] = perm[i % 256];
+        PerlinNoise {
+            permutation: shuffled_permutation(seed),
         }
-
-        PerlinNoise { permutation }
     }
 
     fn fade(t: f32) -> f32 {
@@ -86,6 +93,34 @@ impl PerlinNoise {
         (self.noise2d(x, y) + 1.0) * 0.5
     }
 
+    /// Domain warping: shift the point by two independent noise lookups
+    /// The offsets are in noise-space units, where 1.0 is one lattice cell (roughly one
+    /// feature), and `noise2d` mostly stays within about +-0.7. So a strength of 0.5 bends
+    /// features by a third of their size, 1.0-2.0 gives clearly swirled shapes, and beyond
+    /// that neighboring features start folding into each other. 0.0 leaves the point alone
+    pub fn warp(&self, x: f32, y: f32, strength: f32) -> (f32, f32) {
+        if strength == 0.0 {
+            return (x, y);
+        }
+        // Arbitrary far-apart offsets so the two lookups are uncorrelated
+        let offset_x = self.noise2d(x + 5.2, y + 1.3);
+        let offset_y = self.noise2d(x + 1.7, y + 9.2);
+        (x + strength * offset_x, y + strength * offset_y)
+    }
+
+    /// `noise2d` sampled at a domain-warped point, for swirly organic patterns
+    /// See `warp` for how `warp_strength` maps to distortion
+    #[allow(dead_code)]
+    pub fn warped_noise(&self, x: f32, y: f32, warp_strength: f32) -> f32 {
+        let (warped_x, warped_y) = self.warp(x, y, warp_strength);
+        self.noise2d(warped_x, warped_y)
+    }
+
+    /// Fractal Brownian motion: octaves of `noise2d` summed together
+    /// Octave 0 samples at the base frequency with amplitude 1.0; each following octave
+    /// multiplies the frequency by `lacunarity` and the amplitude by `persistence`
+    /// The sum is divided by the total amplitude, so the result stays in roughly -1.0..1.0
+    /// whatever the octave count (0 octaves gives 0.0)
     pub fn fractal_noise(
         &self,
         x: f32,
@@ -111,9 +146,47 @@ impl PerlinNoise {
             frequency *= lacunarity;
         }
 
-        total / max_value
+        if max_value > 0.0 {
+            total / max_value
+        } else {
+            0.0
+        }
     }
 
+    /// Ridged multifractal: each octave is `1.0 - |noise|`, squared, which turns the zero
+    /// crossings of the noise into sharp crests (mountain ridges)
+    /// Octaves combine with `persistence`/`lacunarity` like `fractal_noise` and the sum is
+    /// normalized by the total amplitude, so the result is in 0.0..1.0 (0 octaves gives 0.0)
+    pub fn ridged_noise(
+        &self,
+        x: f32,
+        y: f32,
+        octaves: u32,
+        persistence: f32,
+        lacunarity: f32,
+    ) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut max_value = 0.0;
+
+        for _ in 0..octaves {
+            let ridge = 1.0 - self.noise2d(x * frequency, y * frequency).abs();
+            total += ridge * ridge * amplitude;
+            max_value += amplitude;
+
+            amplitude *= persistence;
+            frequency *= lacunarity;
+        }
+
+        if max_value > 0.0 {
+            (total / max_value).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// `fractal_noise` remapped to roughly 0.0..1.0
     pub fn fractal_noise_01(
         &self,
         x: f32,
@@ -125,3 +198,66 @@ impl PerlinNoise {
         (self.fractal_noise(x, y, octaves, persistence, lacunarity) + 1.0) * 0.5
     }
 }
+
+/// Cellular (Worley) noise: one jittered feature point per grid cell
+/// Uses the same seeded permutation table as `PerlinNoise`, so results are reproducible
+#[allow(dead_code)]
+pub struct WorleyNoise {
+    permutation: [u8; 512],
+}
+
+#[allow(dead_code)]
+impl WorleyNoise {
+    pub fn new(seed: u32) -> Self {
+        WorleyNoise {
+            permutation: shuffled_permutation(seed),
+        }
+    }
+
+    /// Distance to the nearest feature point (F1), in grid cell units
+    /// 0.0 at a feature point, rarely above 1.0: gives round cells like stones or scales
+    pub fn noise2d(&self, x: f32, y: f32) -> f32 {
+        self.nearest_two(x, y).0
+    }
+
+    /// Second-nearest minus nearest distance (F2 - F1)
+    /// 0.0 on the borders between cells, so it draws a Voronoi edge pattern
+    pub fn cell_edges(&self, x: f32, y: f32) -> f32 {
+        let (f1, f2) = self.nearest_two(x, y);
+        f2 - f1
+    }
+
+    /// Feature point of a grid cell, jittered anywhere inside it
+    fn feature_point(&self, cell_x: i32, cell_y: i32) -> (f32, f32) {
+        let xi = (cell_x & 255) as usize;
+        let yi = (cell_y & 255) as usize;
+        let hash = self.permutation[self.permutation[xi] as usize + yi] as usize;
+        let jitter_x = self.permutation[hash] as f32 / 255.0;
+        let jitter_y = self.permutation[hash + 1] as f32 / 255.0;
+        (cell_x as f32 + jitter_x, cell_y as f32 + jitter_y)
+    }
+
+    /// (F1, F2) over the 3x3 block of cells around the point
+    /// That always holds the nearest feature point; F2 can very rarely lie further out,
+    /// which is the usual trade-off for only checking nine cells
+    fn nearest_two(&self, x: f32, y: f32) -> (f32, f32) {
+        let cell_x = x.floor() as i32;
+        let cell_y = y.floor() as i32;
+
+        let mut f1 = f32::MAX;
+        let mut f2 = f32::MAX;
+        for offset_y in -1..=1 {
+            for offset_x in -1..=1 {
+                let (px, py) = self.feature_point(cell_x + offset_x, cell_y + offset_y);
+                let distance = ((px - x) * (px - x) + (py - y) * (py - y)).sqrt();
+                if distance < f1 {
+                    f2 = f1;
+                    f1 = distance;
+                } else if distance < f2 {
+                    f2 = distance;
+                }
+            }
+        }
+        (f1, f2)
+    }
+}
@@ -1,63 +1,111 @@
 use gl::types::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::{Deref, DerefMut};
+use std::time::Instant;
+
+/// Counter bracketing the whole frame (see `begin_frame`/`end_frame`)
+pub const FRAME_COUNTER: &str = "Frame (GPU)";
+
+/// Number of query objects per timer. Results are read a few frames late,
+/// so the GPU never has to finish the current frame before we can read a timing
+const QUERY_BUFFER_COUNT: usize = 3;
 
 /// GPU timer query for accurate performance measurement
+/// Cycles through several query objects so results are read without stalling.
+/// Each measurement is a pair of GPU timestamps (not a TIME_ELAPSED query),
+/// so timers can be freely nested or overlapped.
 pub struct GpuTimer {
-    query: GLuint,
+    // Start/end timestamp query pair per buffered measurement
+    queries: [[GLuint; 2]; QUERY_BUFFER_COUNT],
+    // Query has been issued and its result not read yet
+    pending: [bool; QUERY_BUFFER_COUNT],
+    // Query to use for the next begin (also the oldest in flight)
+    next: usize,
+    // Query currently between begin and end (None if this frame was skipped)
+    active: Option<usize>,
     last_time_ns: Option<u64>,
-    available: bool,
 }
 
 impl GpuTimer {
     pub fn new() -> Self {
-        let mut query = 0;
+        let mut queries = [[0; 2]; QUERY_BUFFER_COUNT];
         unsafe {
-            gl::GenQueries(1, &mut query);
+            for pair in &mut queries {
+                gl::GenQueries(2, pair.as_mut_ptr());
+            }
         }
         GpuTimer {
-            query,
+            queries,
+            pending: [false; QUERY_BUFFER_COUNT],
+            next: 0,
+            active: None,
             last_time_ns: None,
-            available: true,
         }
     }
 
     /// Start timing - call before rendering
+    /// If every query is still waiting on the GPU this measurement is skipped rather than stalling
     pub fn begin(&mut self) {
+        let index = self.next;
+        if self.pending[index] {
+            self.active = None;
+            return;
+        }
+
         unsafe {
-            gl::BeginQuery(gl::TIME_ELAPSED, self.query);
+            gl::QueryCounter(self.queries[index][0], gl::TIMESTAMP);
         }
+        self.active = Some(index);
     }
 
     /// End timing - call after rendering
     pub fn end(&mut self) {
-        unsafe {
-            gl::EndQuery(gl::TIME_ELAPSED);
+        if let Some(index) = self.active.take() {
+            unsafe {
+                gl::QueryCounter(self.queries[index][1], gl::TIMESTAMP);
+            }
+            self.pending[index] = true;
+            self.next = (index + 1) % QUERY_BUFFER_COUNT;
         }
-        self.available = false;
     }
 
-    /// Try to retrieve results (non-blocking)
-    /// Returns true if results were available
+    /// Try to retrieve results (non-blocking), oldest query first
+    /// Returns true if a new result was read, or if nothing is in flight
     pub fn try_collect(&mut self) -> bool {
-        if self.available {
+        if !self.pending.contains(&true) {
             return true;
         }
 
-        unsafe {
-            let mut available = 0i32;
-            gl::GetQueryObjectiv(self.query, gl::QUERY_RESULT_AVAILABLE, &mut available);
-
-            if available != 0 {
-                let mut time_elapsed = 0u64;
-                gl::GetQueryObjectui64v(self.query, gl::QUERY_RESULT, &mut time_elapsed);
-
-                self.last_time_ns = Some(time_elapsed);
-                self.available = true;
-                true
-            } else {
-                false
+        let mut collected = false;
+        for offset in 0..QUERY_BUFFER_COUNT {
+            let index = (self.next + offset) % QUERY_BUFFER_COUNT;
+            if !self.pending[index] {
+                continue;
             }
+
+            unsafe {
+                // The end timestamp is written last, so once it's ready both are
+                let [start_query, end_query] = self.queries[index];
+                let mut available = 0i32;
+                gl::GetQueryObjectiv(end_query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+
+                // Queries complete in order, so nothing newer is ready either
+                if available == 0 {
+                    break;
+                }
+
+                let mut start_ns = 0u64;
+                let mut end_ns = 0u64;
+                gl::GetQueryObjectui64v(start_query, gl::QUERY_RESULT, &mut start_ns);
+                gl::GetQueryObjectui64v(end_query, gl::QUERY_RESULT, &mut end_ns);
+                self.last_time_ns = Some(end_ns.saturating_sub(start_ns));
+            }
+            self.pending[index] = false;
+            collected = true;
         }
+        collected
     }
 
     /// Get last measured time in milliseconds
@@ -73,14 +121,15 @@ impl GpuTimer {
     /// Reset the timer's stored value to 0
     pub fn reset(&mut self) {
         self.last_time_ns = Some(0);
-        self.available = true;
     }
 }
 
 impl Drop for GpuTimer {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteQueries(1, &self.query);
+            for pair in &self.queries {
+                gl::DeleteQueries(2, pair.as_ptr());
+            }
         }
     }
 }
@@ -92,6 +141,8 @@ pub struct PerformanceCounter {
     history: Vec<f32>,
     history_size: usize,
     current_index: usize,
+    // Number of history slots written so far (min/max/percentiles ignore unwritten slots)
+    filled: usize,
 }
 
 impl PerformanceCounter {
@@ -101,6 +152,7 @@ impl PerformanceCounter {
             history: vec![0.0; history_size],
             history_size,
             current_index: 0,
+            filled: 0,
         }
     }
 
@@ -117,15 +169,73 @@ impl PerformanceCounter {
             let time_ms = self.timer.get_time_ms();
             self.history[self.current_index] = time_ms;
             self.current_index = (self.current_index + 1) % self.history_size;
+            self.filled = (self.filled + 1).min(self.history_size);
             true
         } else {
             false
         }
     }
 
+    /// Average over the samples recorded so far (the whole window once warmed up)
     pub fn get_avg_ms(&self) -> f32 {
-        let sum: f32 = self.history.iter().sum();
-        sum / self.history_size as f32
+        if self.filled == 0 {
+            return 0.0;
+        }
+        let sum: f32 = self.recorded().iter().sum();
+        sum / self.filled as f32
+    }
+
+    /// True once the history window has been filled with real samples
+    pub fn is_warmed_up(&self) -> bool {
+        self.filled == self.history_size
+    }
+
+    /// Discard the history window and start warming up again
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.current_index = 0;
+        self.filled = 0;
+    }
+
+    /// History samples that have actually been recorded (unordered)
+    fn recorded(&self) -> &[f32] {
+        &self.history[..self.filled]
+    }
+
+    /// History window in chronological order (oldest first, newest last)
+    pub fn get_history_ms(&self) -> Vec<f32> {
+        let (newer, older) = self.history.split_at(self.current_index);
+        older.iter().chain(newer).copied().collect()
+    }
+
+    /// Fastest time in the history window
+    pub fn get_min_ms(&self) -> f32 {
+        self.recorded()
+            .iter()
+            .copied()
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    }
+
+    /// Slowest time in the history window
+    pub fn get_max_ms(&self) -> f32 {
+        self.recorded()
+            .iter()
+            .copied()
+            .reduce(f32::max)
+            .unwrap_or(0.0)
+    }
+
+    /// Time below which `p` percent of the history window falls (nearest rank)
+    /// e.g. `get_percentile_ms(99.0)` for the 99th percentile
+    pub fn get_percentile_ms(&self, p: f32) -> f32 {
+        let mut sorted = self.recorded().to_vec();
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f32).ceil() as usize;
+        sorted[rank.saturating_sub(1)]
     }
 
     pub fn get_last_ms(&self) -> f32 {
@@ -138,12 +248,34 @@ impl PerformanceCounter {
     }
 }
 
+/// Upper bounds (ms) of the frame-time histogram buckets; the last bucket is open-ended
+const FRAME_HISTOGRAM_BOUNDS: [f32; 3] = [8.0, 16.0, 33.0];
+const FRAME_HISTOGRAM_LABELS: [&str; 4] = ["< 8 ms", "8-16 ms", "16-33 ms", "> 33 ms"];
+
+/// Called from `update` for every counter over its budget: (name, last_ms, budget_ms)
+pub type BudgetCallback = Box<dyn FnMut(&str, f32, f32)>;
+
 /// Central performance monitoring system
 /// Automatically tracks all registered counters
 pub struct PerformanceMonitor {
     counters: HashMap<String, PerformanceCounter>,
     history_size: usize,
     enabled: bool,
+    // Per-frame snapshots of every counter's last value, captured while recording
+    recording: bool,
+    recorded_frames: Vec<BTreeMap<String, f32>>,
+    // Scopes currently between begin and end (innermost last)
+    open_scopes: Vec<String>,
+    // CPU time spent between begin_frame and end_frame
+    frame_start: Option<Instant>,
+    cpu_frame_ms: f32,
+    // Per-counter time budgets in milliseconds
+    budgets: HashMap<String, f32>,
+    budget_callback: Option<BudgetCallback>,
+    // Number of frames whose total time fell in each histogram bucket
+    frame_histogram: [u64; 4],
+    // Non-timing per-frame values, e.g. how many objects were culled
+    frame_stats: BTreeMap<String, usize>,
 }
 
 #[allow(dead_code)]
@@ -153,6 +285,15 @@ impl PerformanceMonitor {
             counters: HashMap::new(),
             history_size,
             enabled: true,
+            recording: false,
+            recorded_frames: Vec::new(),
+            open_scopes: Vec::new(),
+            frame_start: None,
+            cpu_frame_ms: 0.0,
+            budgets: HashMap::new(),
+            budget_callback: None,
+            frame_histogram: [0; 4],
+            frame_stats: BTreeMap::new(),
         }
     }
 
@@ -164,31 +305,209 @@ impl PerformanceMonitor {
     }
 
     /// Start timing for a named operation
+    /// Scopes may be nested or overlap (e.g. a bloom stage inside a frame-total scope),
+    /// but the same name can't be open twice at once
     pub fn begin(&mut self, name: &str) {
         if !self.enabled {
             return;
         }
+        if self.open_scopes.iter().any(|open| open == name) {
+            eprintln!(
+                "PerformanceMonitor: '{}' begun again before it ended; ignoring",
+                name
+            );
+            return;
+        }
+        self.open_scopes.push(name.to_string());
         self.ensure_counter(name).begin();
     }
 
+    /// Start timing a named operation that ends when the returned guard is dropped
+    /// The guard derefs to the monitor, so nested scopes can be opened through it
+    pub fn scope(&mut self, name: &str) -> PerfScope<'_> {
+        self.begin(name);
+        PerfScope {
+            monitor: self,
+            name: name.to_string(),
+        }
+    }
+
     /// End timing for a named operation
     pub fn end(&mut self, name: &str) {
         if !self.enabled {
             return;
         }
+        match self.open_scopes.iter().rposition(|open| open == name) {
+            Some(index) => {
+                self.open_scopes.remove(index);
+            }
+            None => {
+                eprintln!(
+                    "PerformanceMonitor: '{}' ended without begin; ignoring",
+                    name
+                );
+                return;
+            }
+        }
         if let Some(counter) = self.counters.get_mut(name) {
             counter.end();
         }
     }
 
+    /// Names of scopes that have begun but not ended yet (innermost last)
+    pub fn open_scopes(&self) -> &[String] {
+        &self.open_scopes
+    }
+
     /// Update all counters (call once per frame)
     pub fn update(&mut self) {
         if !self.enabled {
             return;
         }
-        for counter in self.counters.values_mut() {
-            counter.update();
+        let mut frame_collected = false;
+        let mut any_collected = false;
+        for (name, counter) in self.counters.iter_mut() {
+            let collected = counter.update();
+            any_collected |= collected;
+            if name == FRAME_COUNTER {
+                frame_collected = collected;
+            }
+        }
+
+        // Bucket the total time only when a new result arrived, so warm-up frames
+        // and frames whose queries are still pending don't count the same time again
+        let fresh = if self.counters.contains_key(FRAME_COUNTER) {
+            frame_collected
+        } else {
+            any_collected
+        };
+        if fresh {
+            let frame_ms = self.get_total_time_ms();
+            let bucket = FRAME_HISTOGRAM_BOUNDS
+                .iter()
+                .position(|&bound| frame_ms < bound)
+                .unwrap_or(FRAME_HISTOGRAM_BOUNDS.len());
+            self.frame_histogram[bucket] += 1;
+        }
+
+        if let Some(callback) = self.budget_callback.as_mut() {
+            for (name, budget_ms) in &self.budgets {
+                if let Some(counter) = self.counters.get(name) {
+                    let last_ms = counter.get_last_ms();
+                    if last_ms > *budget_ms {
+                        callback(name, last_ms, *budget_ms);
+                    }
+                }
+            }
+        }
+
+        // A scope left open would never produce a result - report it once and drop it
+        for name in self.open_scopes.drain(..) {
+            eprintln!("PerformanceMonitor: '{}' was never ended this frame", name);
+        }
+
+        if self.recording {
+            let frame = self
+                .counters
+                .iter()
+                .map(|(name, counter)| (name.clone(), counter.get_last_ms()))
+                .collect();
+            self.recorded_frames.push(frame);
+        }
+    }
+
+    /// Frame-time distribution since startup (or the last reset): (bucket label, frame count)
+    pub fn get_frame_histogram(&self) -> Vec<(&'static str, u64)> {
+        FRAME_HISTOGRAM_LABELS
+            .iter()
+            .copied()
+            .zip(self.frame_histogram.iter().copied())
+            .collect()
+    }
+
+    /// Clear the frame-time histogram
+    pub fn reset_histogram(&mut self) {
+        self.frame_histogram = [0; 4];
+    }
+
+    /// Set a time budget for a counter in milliseconds
+    pub fn set_budget(&mut self, name: &str, budget_ms: f32) {
+        self.budgets.insert(name.to_string(), budget_ms);
+    }
+
+    /// Remove a counter's time budget
+    pub fn clear_budget(&mut self, name: &str) {
+        self.budgets.remove(name);
+    }
+
+    /// Call `callback(name, last_ms, budget_ms)` from `update` for every counter over budget
+    pub fn set_budget_callback(&mut self, callback: BudgetCallback) {
+        self.budget_callback = Some(callback);
+    }
+
+    /// Counters whose last time exceeded their budget: (name, last_ms, budget_ms), sorted by name
+    pub fn get_over_budget(&self) -> Vec<(String, f32, f32)> {
+        let mut over: Vec<_> = self
+            .budgets
+            .iter()
+            .filter_map(|(name, &budget_ms)| {
+                let last_ms = self.counters.get(name)?.get_last_ms();
+                (last_ms > budget_ms).then(|| (name.clone(), last_ms, budget_ms))
+            })
+            .collect();
+        over.sort_by(|a, b| a.0.cmp(&b.0));
+        over
+    }
+
+    /// Start capturing one row of counter values per frame (clears any previous capture)
+    pub fn start_recording(&mut self) {
+        self.recorded_frames.clear();
+        self.recording = true;
+    }
+
+    /// Stop capturing; the captured frames are kept until the next `start_recording`
+    pub fn stop_recording(&mut self) {
+        self.recording = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Number of frames captured so far
+    pub fn recorded_frame_count(&self) -> usize {
+        self.recorded_frames.len()
+    }
+
+    /// Write the captured frames to a CSV file: one row per frame, one column per counter (ms)
+    /// Counters that didn't exist yet in a frame are left empty
+    pub fn export_csv(&self, path: &str) -> std::io::Result<()> {
+        let columns: BTreeSet<&String> = self
+            .recorded_frames
+            .iter()
+            .flat_map(|frame| frame.keys())
+            .collect();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        write!(writer, "frame")?;
+        for name in &columns {
+            write!(writer, ",{}", name)?;
         }
+        writeln!(writer)?;
+
+        for (i, frame) in self.recorded_frames.iter().enumerate() {
+            write!(writer, "{}", i)?;
+            for name in &columns {
+                match frame.get(*name) {
+                    Some(ms) => write!(writer, ",{:.4}", ms)?,
+                    None => write!(writer, ",")?,
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()
     }
 
     /// Get average time for a counter in milliseconds
@@ -201,11 +520,50 @@ impl PerformanceMonitor {
         self.counters.get(name).map(|c| c.get_last_ms())
     }
 
+    /// Get a counter's history window in chronological order (oldest first)
+    pub fn get_history_ms(&self, name: &str) -> Option<Vec<f32>> {
+        self.counters.get(name).map(|c| c.get_history_ms())
+    }
+
+    /// Get the slowest time in a counter's history window in milliseconds
+    pub fn get_max_ms(&self, name: &str) -> Option<f32> {
+        self.counters.get(name).map(|c| c.get_max_ms())
+    }
+
+    /// Get the fastest time in a counter's history window in milliseconds
+    pub fn get_min_ms(&self, name: &str) -> Option<f32> {
+        self.counters.get(name).map(|c| c.get_min_ms())
+    }
+
+    /// Get a percentile (0-100) of a counter's history window in milliseconds
+    pub fn get_percentile_ms(&self, name: &str, p: f32) -> Option<f32> {
+        self.counters.get(name).map(|c| c.get_percentile_ms(p))
+    }
+
+    /// Whether a counter's history window has filled up (its averages are stable)
+    pub fn is_warmed_up(&self, name: &str) -> Option<bool> {
+        self.counters.get(name).map(|c| c.is_warmed_up())
+    }
+
+    /// True once every counter's history window has filled up
+    pub fn all_warmed_up(&self) -> bool {
+        self.counters.values().all(|c| c.is_warmed_up())
+    }
+
+    /// Clear every counter's history so averages start over (e.g. after changing settings)
+    pub fn reset_counters(&mut self) {
+        for counter in self.counters.values_mut() {
+            counter.reset();
+        }
+    }
+
     /// Get all counter names and their average times (sorted by name)
+    /// The whole-frame counter is left out; use `get_frame_gpu_ms` for it
     pub fn get_all_counters(&self) -> Vec<(String, f32, f32)> {
         let mut counters: Vec<_> = self
             .counters
             .iter()
+            .filter(|(name, _)| name.as_str() != FRAME_COUNTER)
             .map(|(name, counter)| (name.clone(), counter.get_last_ms(), counter.get_avg_ms()))
             .collect();
         counters.sort_by(|a, b| a.0.cmp(&b.0));
@@ -236,8 +594,83 @@ impl PerformanceMonitor {
         }
     }
 
-    /// Get total render time by summing all counters
+    /// Start a new frame: resets all counters and opens the whole-frame GPU scope
+    /// Call once per frame before any rendering
+    pub fn begin_frame(&mut self) {
+        self.reset_frame();
+        self.frame_start = Some(Instant::now());
+        self.begin(FRAME_COUNTER);
+    }
+
+    /// Close the whole-frame GPU scope and record the CPU frame time
+    /// Call after the last draw of the frame (before swapping buffers)
+    pub fn end_frame(&mut self) {
+        self.end(FRAME_COUNTER);
+        if let Some(start) = self.frame_start.take() {
+            self.cpu_frame_ms = start.elapsed().as_secs_f32() * 1000.0;
+        }
+    }
+
+    /// Record a per-frame count under `name`, replacing last frame's value
+    pub fn set_frame_stat(&mut self, name: &str, value: usize) {
+        self.frame_stats.insert(name.to_string(), value);
+    }
+
+    /// Every value recorded with `set_frame_stat`, sorted by name
+    pub fn get_frame_stats(&self) -> &BTreeMap<String, usize> {
+        &self.frame_stats
+    }
+
+    /// GPU time of the whole frame (everything between begin_frame and end_frame)
+    pub fn get_frame_gpu_ms(&self) -> Option<f32> {
+        self.get_last_ms(FRAME_COUNTER)
+    }
+
+    /// CPU time spent between begin_frame and end_frame of the last frame
+    pub fn get_frame_cpu_ms(&self) -> f32 {
+        self.cpu_frame_ms
+    }
+
+    /// GPU frame time divided by CPU frame time
+    /// Above 1.0 the GPU is the bottleneck, below 1.0 the CPU is
+    pub fn get_gpu_cpu_ratio(&self) -> Option<f32> {
+        let gpu_ms = self.get_frame_gpu_ms()?;
+        (self.cpu_frame_ms > 0.0).then(|| gpu_ms / self.cpu_frame_ms)
+    }
+
+    /// Get total render time
+    /// Uses the whole-frame counter when available; otherwise sums the individual
+    /// counters (which double-counts nested scopes and misses untimed work)
     pub fn get_total_time_ms(&self) -> f32 {
-        self.counters.values().map(|c| c.get_last_ms()).sum()
+        match self.get_frame_gpu_ms() {
+            Some(frame_ms) => frame_ms,
+            None => self.counters.values().map(|c| c.get_last_ms()).sum(),
+        }
+    }
+}
+
+/// Guard returned by `PerformanceMonitor::scope`; ends the scope when dropped
+pub struct PerfScope<'a> {
+    monitor: &'a mut PerformanceMonitor,
+    name: String,
+}
+
+impl Deref for PerfScope<'_> {
+    type Target = PerformanceMonitor;
+
+    fn deref(&self) -> &PerformanceMonitor {
+        self.monitor
+    }
+}
+
+impl DerefMut for PerfScope<'_> {
+    fn deref_mut(&mut self) -> &mut PerformanceMonitor {
+        self.monitor
+    }
+}
+
+impl Drop for PerfScope<'_> {
+    fn drop(&mut self) {
+        self.monitor.end(&self.name);
     }
 }
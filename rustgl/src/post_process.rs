@@ -0,0 +1,186 @@
+use crate::bloom_renderer::BloomRenderer;
+use crate::godray_renderer::GodRayRenderer;
+use crate::performance_monitor::PerformanceMonitor;
+use crate::scene::{Scene, SceneObjectTag};
+use crate::shader::Shader;
+use nalgebra_glm as glm;
+
+/// Owns the post-processing renderers and runs them in order:
+/// scene -> bloom (+ tone mapping) -> god rays -> screen
+/// or, with HDR god rays: scene -> god rays -> bloom (+ rays, tone mapping) -> screen
+///
+/// Every stage is timed with the performance monitor: the bloom and godray renderers
+/// time their own passes and the stack adds labels for the stages that aren't
+/// already instrumented.
+pub struct PostProcessStack {
+    pub bloom: BloomRenderer,
+    pub godrays: GodRayRenderer,
+
+    // Per-frame settings
+    pub bloom_enabled: bool,
+    pub bloom_threshold: f32,
+    pub bloom_strength: f32,
+    pub godray_strength: f32,
+    pub godray_debug_mode: u8, // 0 = off, 1 = occlusion, 2 = radial blur, 3 = rays only
+    // Let the hardware sRGB-encode the final draw (GL_FRAMEBUFFER_SRGB) instead of the
+    // composite shader; the window must have an sRGB-capable framebuffer. Turn the bloom
+    // composite's gamma correction off alongside it or the output is encoded twice
+    pub srgb_output: bool,
+
+    // Output size in pixels
+    width: i32,
+    height: i32,
+}
+
+impl PostProcessStack {
+    /// `hdr_godrays` renders god rays into float buffers and adds them before tone mapping;
+    /// otherwise they are composited on top of the tone-mapped bloom output
+    pub fn new(
+        width: u32,
+        height: u32,
+        bloom_scale: f32,
+        godray_scale: f32,
+        hdr_godrays: bool,
+    ) -> Self {
+        PostProcessStack {
+            bloom: BloomRenderer::new(width, height, bloom_scale),
+            godrays: GodRayRenderer::new(width, height, godray_scale, hdr_godrays),
+            bloom_enabled: true,
+            bloom_threshold: 1.0,
+            bloom_strength: 1.0,
+            godray_strength: 1.0,
+            godray_debug_mode: 0,
+            srgb_output: false,
+            width: width as i32,
+            height: height as i32,
+        }
+    }
+
+    /// Resize every stage (call when the framebuffer size changes)
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as i32;
+        self.height = height as i32;
+        self.bloom.resize(width, height);
+        self.godrays.resize(width, height);
+    }
+
+    /// Every shader used by the post-processing stages, e.g. for hot reloading
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        let mut shaders = self.bloom.shaders_mut();
+        shaders.extend(self.godrays.shaders_mut());
+        shaders
+    }
+
+    /// Render the scene through the whole post-processing chain to the screen
+    pub fn render<F>(
+        &mut self,
+        render_scene: F,
+        scene: &Scene,
+        view: &glm::Mat4,
+        projection: &glm::Mat4,
+        perf_monitor: &mut PerformanceMonitor,
+    ) where
+        F: FnOnce(),
+    {
+        // Only sRGB framebuffers are affected, so the offscreen passes stay linear and just
+        // the final draw to the window is encoded. Off again before the UI, whose colors
+        // are already sRGB
+        if self.srgb_output {
+            unsafe { gl::Enable(gl::FRAMEBUFFER_SRGB) };
+        }
+        self.render_stages(render_scene, scene, view, projection, perf_monitor);
+        if self.srgb_output {
+            unsafe { gl::Disable(gl::FRAMEBUFFER_SRGB) };
+        }
+    }
+
+    fn render_stages<F>(
+        &mut self,
+        render_scene: F,
+        scene: &Scene,
+        view: &glm::Mat4,
+        projection: &glm::Mat4,
+        perf_monitor: &mut PerformanceMonitor,
+    ) where
+        F: FnOnce(),
+    {
+        // Every object tagged as a godray source casts rays from its own position
+        let godray_sources = scene.find_objects_by_tag(SceneObjectTag::GodraySource);
+
+        if self.godrays.is_hdr() {
+            // HDR rays are light like any other: render them right after the scene and
+            // let the bloom composite add them in linear space before tone mapping
+            self.bloom.render_scene_pass(render_scene, perf_monitor);
+            let rays =
+                self.godrays
+                    .render_rays(scene, &godray_sources, view, projection, perf_monitor);
+            self.bloom.set_godrays(Some(rays), self.godray_strength);
+            self.bloom.finish(
+                self.bloom_threshold,
+                self.bloom_strength,
+                self.bloom_enabled,
+                self.width,
+                self.height,
+                perf_monitor,
+            );
+
+            if self.godray_debug_mode == 0 || self.is_bloom_debug_view() {
+                self.present_composite(perf_monitor);
+            } else {
+                self.godrays.present(
+                    self.bloom.composite_texture(),
+                    self.godray_strength,
+                    self.godray_debug_mode,
+                    self.width,
+                    self.height,
+                    perf_monitor,
+                );
+            }
+            return;
+        }
+
+        // LDR: Scene + bloom + tone mapping (into the bloom composite texture)
+        self.bloom.set_godrays(None, 0.0);
+        self.bloom.render(
+            render_scene,
+            self.bloom_threshold,
+            self.bloom_strength,
+            self.bloom_enabled,
+            self.width,
+            self.height,
+            perf_monitor,
+        );
+
+        // Then god rays on top of the composite, drawn to the screen
+        if self.is_bloom_debug_view() {
+            // Show the bloom debug buffer on its own, without god rays on top
+            self.present_composite(perf_monitor);
+            return;
+        }
+
+        self.godrays.apply(
+            self.bloom.composite_texture(),
+            scene,
+            &godray_sources,
+            view,
+            projection,
+            self.godray_strength,
+            self.godray_debug_mode,
+            self.width,
+            self.height,
+            perf_monitor,
+        );
+    }
+
+    fn is_bloom_debug_view(&self) -> bool {
+        self.bloom_enabled && self.bloom.is_debug_view()
+    }
+
+    /// Copy the bloom composite to the screen as-is
+    fn present_composite(&self, perf_monitor: &mut PerformanceMonitor) {
+        perf_monitor.begin("8. Present");
+        self.godrays
+            .render_passthrough(self.bloom.composite_texture(), self.width, self.height);
+        perf_monitor.end("8. Present");
+    }
+}
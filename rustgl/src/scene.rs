@@ -1,10 +1,17 @@
+use crate::animator::Animator;
+use crate::camera::{box_in_frustum, frustum_planes};
 use crate::light::Light;
 use crate::material::Material;
-use crate::mesh::Mesh;
+use crate::mesh::{ray_box_distance, Mesh};
 use crate::shader::Shader;
 use crate::texture::Texture;
 use crate::transform::Transform;
 use nalgebra_glm as glm;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Color added to the selected object so it stands out
+const SELECTION_HIGHLIGHT: [f32; 3] = [0.25, 0.2, 0.0];
 
 /// Tags for identifying special scene objects
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,11 +19,23 @@ pub enum SceneObjectTag {
     GodraySource, // The light source for godray effect
 }
 
+/// Refers to a scene object by its index, as returned by `Scene::add_named_object`
+pub type ObjectHandle = usize;
+
 pub struct SceneObject {
     pub mesh: Mesh,
     pub material: Material,
     pub transform: Transform,
     pub tags: Vec<SceneObjectTag>,
+    pub animator: Option<Animator>,
+    pub name: Option<String>, // Unique within the scene, see `Scene::add_named_object`
+    // `transform` is relative to this object, see `Scene::world_matrix`
+    pub parent: Option<ObjectHandle>,
+    // Sampled for the base color instead of the vertex colors (shared, so one loaded
+    // texture can go on several objects)
+    pub diffuse_map: Option<Rc<Texture>>,
+    // Tangent-space normal map perturbing the lighting normals (load it as linear, not sRGB)
+    pub normal_map: Option<Rc<Texture>>,
 }
 
 impl SceneObject {
@@ -26,6 +45,15 @@ impl SceneObject {
     }
 }
 
+/// Many copies of one mesh drawn with a single instanced draw call
+/// The transforms are uploaded once when the group is added
+pub struct InstancedObject {
+    pub mesh: Mesh,
+    pub material: Material,
+    pub transforms: Vec<Transform>,
+    pub visible: bool,
+}
+
 pub struct Skybox {
     pub mesh: Mesh,
     pub shader: Shader,
@@ -39,6 +67,11 @@ impl SceneObject {
             material,
             transform,
             tags: Vec::new(),
+            animator: None,
+            name: None,
+            parent: None,
+            diffuse_map: None,
+            normal_map: None,
         }
     }
 
@@ -53,12 +86,49 @@ impl SceneObject {
             self.tags.push(tag);
         }
     }
+
+    /// World-space bounding box as (min, max) corners, for the given model matrix
+    /// (usually `Scene::world_matrix`, so parents are included)
+    /// Transforms the mesh's eight local corners, so the box stays enclosing under rotation
+    pub fn world_bounds(&self, model: &glm::Mat4) -> (glm::Vec3, glm::Vec3) {
+        let (local_min, local_max) = self.mesh.bounds();
+
+        let mut world_min = glm::vec3(f32::MAX, f32::MAX, f32::MAX);
+        let mut world_max = glm::vec3(f32::MIN, f32::MIN, f32::MIN);
+        for corner in 0..8 {
+            let local = glm::vec4(
+                if corner & 1 == 0 {
+                    local_min.x
+                } else {
+                    local_max.x
+                },
+                if corner & 2 == 0 {
+                    local_min.y
+                } else {
+                    local_max.y
+                },
+                if corner & 4 == 0 {
+                    local_min.z
+                } else {
+                    local_max.z
+                },
+                1.0,
+            );
+            let world = (model * local).xyz();
+            world_min = glm::min2(&world_min, &world);
+            world_max = glm::max2(&world_max, &world);
+        }
+        (world_min, world_max)
+    }
 }
 
 pub struct Scene {
     objects: Vec<SceneObject>,
+    instanced: Vec<InstancedObject>,
     lights: Vec<Light>,
     skybox: Option<Skybox>,
+    selected: Option<usize>,   // Highlighted object, e.g. from mouse picking
+    culled_count: Cell<usize>, // Objects skipped by the last `render` for being off-screen
 }
 
 #[allow(dead_code)]
@@ -66,8 +136,11 @@ impl Scene {
     pub fn new() -> Self {
         Scene {
             objects: Vec::new(),
+            instanced: Vec::new(),
             lights: Vec::new(),
             skybox: None,
+            selected: None,
+            culled_count: Cell::new(0),
         }
     }
 
@@ -76,6 +149,52 @@ impl Scene {
             .push(SceneObject::new(mesh, material, transform));
     }
 
+    /// Add an object that can be looked up with `get_by_name` and return its index
+    /// Names are kept unique: a duplicate gets a numeric suffix ("cube" -> "cube_2", "cube_3", ...)
+    pub fn add_named_object(
+        &mut self,
+        name: &str,
+        mesh: Mesh,
+        material: Material,
+        transform: Transform,
+    ) -> usize {
+        let mut unique_name = name.to_string();
+        let mut suffix = 2;
+        while self.find_by_name(&unique_name).is_some() {
+            unique_name = format!("{}_{}", name, suffix);
+            suffix += 1;
+        }
+
+        let mut object = SceneObject::new(mesh, material, transform);
+        object.name = Some(unique_name);
+        self.objects.push(object);
+        self.objects.len() - 1
+    }
+
+    /// Add an instanced group and return its index
+    pub fn add_instanced(
+        &mut self,
+        mut mesh: Mesh,
+        material: Material,
+        transforms: Vec<Transform>,
+    ) -> usize {
+        let matrices: Vec<glm::Mat4> = transforms.iter().map(|t| t.to_matrix()).collect();
+        mesh.set_instance_transforms(&matrices);
+        self.instanced.push(InstancedObject {
+            mesh,
+            material,
+            transforms,
+            visible: true,
+        });
+        self.instanced.len() - 1
+    }
+
+    pub fn set_instanced_visible(&mut self, index: usize, visible: bool) {
+        if let Some(group) = self.instanced.get_mut(index) {
+            group.visible = visible;
+        }
+    }
+
     pub fn add_light(&mut self, light: Light) {
         self.lights.push(light);
     }
@@ -92,10 +211,72 @@ impl Scene {
         self.objects.get_mut(index)
     }
 
+    /// Index of the object with this name
+    pub fn find_by_name(&self, name: &str) -> Option<usize> {
+        self.objects
+            .iter()
+            .position(|obj| obj.name.as_deref() == Some(name))
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&SceneObject> {
+        self.find_by_name(name).map(|index| &self.objects[index])
+    }
+
+    pub fn get_by_name_mut(&mut self, name: &str) -> Option<&mut SceneObject> {
+        self.find_by_name(name)
+            .map(move |index| &mut self.objects[index])
+    }
+
     pub fn object_count(&self) -> usize {
         self.objects.len()
     }
 
+    /// Highlight an object (None clears the selection)
+    pub fn set_selected(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&index| index < self.objects.len());
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Find the nearest object hit by a world-space ray
+    /// The ray is moved into each object's local space, so hits are exact against the
+    /// triangles rather than an approximate bounding volume
+    pub fn pick(&self, origin: glm::Vec3, dir: glm::Vec3) -> Option<usize> {
+        let mut nearest: Option<(usize, f32)> = None;
+        for (index, object) in self.objects.iter().enumerate() {
+            let inverse_model = glm::inverse(&self.world_matrix(index));
+            let local_origin = (inverse_model * glm::vec4(origin.x, origin.y, origin.z, 1.0)).xyz();
+            let local_dir = (inverse_model * glm::vec4(dir.x, dir.y, dir.z, 0.0)).xyz();
+
+            // The local direction isn't renormalized, so the hit distance stays in
+            // world-ray units and can be compared across objects
+            if let Some(distance) = object.mesh.raycast(local_origin, local_dir) {
+                if nearest.is_none_or(|(_, nearest)| distance < nearest) {
+                    nearest = Some((index, distance));
+                }
+            }
+        }
+        nearest.map(|(index, _)| index)
+    }
+
+    /// Find the nearest object whose world bounding box the ray hits, with the distance
+    /// Cheaper but coarser than `pick`: a ray starting inside a box hits it at 0.0, and
+    /// boxes behind the origin are ignored
+    pub fn raycast(&self, origin: glm::Vec3, dir: glm::Vec3) -> Option<(ObjectHandle, f32)> {
+        let mut nearest: Option<(ObjectHandle, f32)> = None;
+        for (index, object) in self.objects.iter().enumerate() {
+            let (box_min, box_max) = object.world_bounds(&self.world_matrix(index));
+            if let Some(distance) = ray_box_distance(origin, dir, box_min, box_max) {
+                if nearest.is_none_or(|(_, nearest)| distance < nearest) {
+                    nearest = Some((index, distance));
+                }
+            }
+        }
+        nearest
+    }
+
     pub fn objects_iter(&self) -> std::slice::Iter<'_, SceneObject> {
         self.objects.iter()
     }
@@ -105,6 +286,16 @@ impl Scene {
         self.objects.iter().position(|obj| obj.has_tag(tag))
     }
 
+    /// Find the indices of all objects with a specific tag
+    pub fn find_objects_by_tag(&self, tag: SceneObjectTag) -> Vec<usize> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(_, obj)| obj.has_tag(tag))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     /// Tag a specific object by index
     pub fn tag_object(&mut self, index: usize, tag: SceneObjectTag) {
         if let Some(obj) = self.objects.get_mut(index) {
@@ -112,6 +303,116 @@ impl Scene {
         }
     }
 
+    /// Make `child`'s transform relative to `parent` (None detaches it)
+    /// A parent that would create a cycle is rejected and logged
+    pub fn set_parent(&mut self, child: ObjectHandle, parent: Option<ObjectHandle>) {
+        if child >= self.objects.len() || parent.is_some_and(|p| p >= self.objects.len()) {
+            return;
+        }
+
+        // Walking up from the new parent must not reach the child
+        let mut ancestor = parent;
+        let mut steps = 0;
+        while let Some(index) = ancestor {
+            if index == child || steps > self.objects.len() {
+                eprintln!(
+                    "Scene: parenting object {} to {:?} would create a cycle, ignoring",
+                    child, parent
+                );
+                return;
+            }
+            ancestor = self.objects[index].parent;
+            steps += 1;
+        }
+        self.objects[child].parent = parent;
+    }
+
+    /// Model matrix of an object in world space: its own transform combined with
+    /// every parent's, innermost last
+    /// A cycle in the parent chain (possible by editing `parent` directly) is logged and
+    /// the object is treated as having no parent
+    pub fn world_matrix(&self, index: ObjectHandle) -> glm::Mat4 {
+        let Some(object) = self.objects.get(index) else {
+            return glm::Mat4::identity();
+        };
+
+        let mut matrix = object.transform.to_matrix();
+        let mut ancestor = object.parent;
+        let mut steps = 0;
+        while let Some(parent) = ancestor.and_then(|i| self.objects.get(i)) {
+            steps += 1;
+            if steps > self.objects.len() {
+                eprintln!(
+                    "Scene: parent cycle above object {}, ignoring its parent",
+                    index
+                );
+                return object.transform.to_matrix();
+            }
+            matrix = parent.transform.to_matrix() * matrix;
+            ancestor = parent.parent;
+        }
+        matrix
+    }
+
+    /// World-space position of an object's origin
+    pub fn world_position(&self, index: ObjectHandle) -> glm::Vec3 {
+        self.world_matrix(index).column(3).xyz()
+    }
+
+    /// Attach an animator to a specific object by index (replaces any existing one)
+    pub fn set_animator(&mut self, index: usize, animator: Animator) {
+        if let Some(obj) = self.objects.get_mut(index) {
+            obj.animator = Some(animator);
+        }
+    }
+
+    /// Give an object a diffuse texture (None goes back to its vertex colors)
+    pub fn set_diffuse_map(&mut self, index: usize, texture: Option<Rc<Texture>>) {
+        if let Some(obj) = self.objects.get_mut(index) {
+            obj.diffuse_map = texture;
+        }
+    }
+
+    /// Give an object a tangent-space normal map (None goes back to the mesh normals)
+    pub fn set_normal_map(&mut self, index: usize, texture: Option<Rc<Texture>>) {
+        if let Some(obj) = self.objects.get_mut(index) {
+            obj.normal_map = texture;
+        }
+    }
+
+    /// Advance every object's animator and move the lights attached to them
+    /// `time` is the total animation time in seconds
+    pub fn update_animations(&mut self, delta_time: f32, time: f32) {
+        for obj in &mut self.objects {
+            let Some(animator) = obj.animator else {
+                continue;
+            };
+            let transform = &mut obj.transform;
+            animator.apply(
+                &mut transform.position,
+                &mut transform.rotation,
+                delta_time,
+                time,
+            );
+        }
+
+        // Lights follow the world position, once every parent has moved
+        for index in 0..self.objects.len() {
+            let Some(light_index) = self.objects[index].animator.and_then(|a| a.light_index) else {
+                continue;
+            };
+            let position = self.world_position(index);
+            if let Some(light) = self.lights.get_mut(light_index) {
+                light.position = position;
+            }
+        }
+    }
+
+    /// Mutable access to a light by index
+    pub fn get_light_mut(&mut self, index: usize) -> Option<&mut Light> {
+        self.lights.get_mut(index)
+    }
+
     /// Update the position of a specific light by index
     pub fn update_light_position(&mut self, index: usize, position: glm::Vec3) {
         if let Some(light) = self.lights.get_mut(index) {
@@ -119,6 +420,14 @@ impl Scene {
         }
     }
 
+    /// Shaders owned by the scene (the skybox's), e.g. for hot reloading
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        self.skybox
+            .iter_mut()
+            .map(|skybox| &mut skybox.shader)
+            .collect()
+    }
+
     /// Set the skybox for the scene
     pub fn set_skybox(&mut self, mesh: Mesh, shader: Shader, texture: Texture) {
         self.skybox = Some(Skybox {
@@ -134,6 +443,7 @@ impl Scene {
         view: &glm::Mat4,
         projection: &glm::Mat4,
         skybox_enabled: bool,
+        textures_enabled: bool, // false draws every object with its vertex colors
     ) {
         // Render skybox first (if present and enabled)
         if skybox_enabled {
@@ -159,11 +469,86 @@ impl Scene {
         shader.set_mat4("projection", projection);
 
         shader.set_lights(&self.lights);
+        shader.set_int("textureSampler", 0);
+        shader.set_int("normalMap", 1);
+
+        let highlight = glm::make_vec3(&SELECTION_HIGHLIGHT);
+        let no_highlight = glm::vec3(0.0, 0.0, 0.0);
+        let frustum = frustum_planes(&(projection * view));
+        let mut culled = 0;
+        for (index, object) in self.objects.iter().enumerate() {
+            // Skip objects whose bounding box is entirely off-screen
+            let model = self.world_matrix(index);
+            let (min, max) = object.world_bounds(&model);
+            if !box_in_frustum(&frustum, &min, &max) {
+                culled += 1;
+                continue;
+            }
 
-        for object in &self.objects {
             shader.set_material(&object.material);
-            shader.set_mat4("model", &object.transform.to_matrix());
+            shader.set_vec3(
+                "highlightColor",
+                if self.selected == Some(index) {
+                    &highlight
+                } else {
+                    &no_highlight
+                },
+            );
+            shader.set_mat4("model", &model);
+
+            // Objects without a map fall back to their vertex colors
+            match object.diffuse_map.as_ref().filter(|_| textures_enabled) {
+                Some(texture) => {
+                    texture.bind(0);
+                    shader.set_bool("useTexture", true);
+                }
+                None => shader.set_bool("useTexture", false),
+            }
+            match object.normal_map.as_ref().filter(|_| textures_enabled) {
+                Some(texture) => {
+                    texture.bind(1);
+                    shader.set_bool("useNormalMap", true);
+                }
+                None => shader.set_bool("useNormalMap", false),
+            }
+
+            object.mesh.draw();
+        }
+        self.culled_count.set(culled);
+
+        // Instanced groups: one draw call each, model matrices come from the instance VBO
+        shader.set_vec3("highlightColor", &no_highlight);
+        shader.set_bool("useTexture", false);
+        shader.set_bool("useNormalMap", false);
+        shader.set_bool("instanced", true);
+        for group in self.instanced.iter().filter(|group| group.visible) {
+            shader.set_material(&group.material);
+            group.mesh.draw_instanced(group.transforms.len() as i32);
+        }
+        shader.set_bool("instanced", false);
+    }
+
+    /// Number of objects frustum culling skipped in the last `render`
+    pub fn culled_count(&self) -> usize {
+        self.culled_count.get()
+    }
+
+    /// Draw each object's vertex normals as lines (needs the normals geometry shader)
+    pub fn render_normals(
+        &self,
+        shader: &Shader,
+        view: &glm::Mat4,
+        projection: &glm::Mat4,
+        length: f32,
+    ) {
+        shader.use_program();
+        shader.set_mat4("view", view);
+        shader.set_mat4("projection", projection);
+        shader.set_float("normalLength", length);
+        shader.set_vec3("lineColor", &glm::vec3(1.0, 1.0, 0.0));
 
+        for (index, object) in self.objects.iter().enumerate() {
+            shader.set_mat4("model", &self.world_matrix(index));
             object.mesh.draw();
         }
     }
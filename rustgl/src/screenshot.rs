@@ -0,0 +1,47 @@
+use gl::types::*;
+use image::RgbaImage;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Read the default framebuffer's back buffer (what will be shown on the next swap)
+/// Returns tightly packed RGBA8 rows, bottom row first, like `Framebuffer::read_pixels`
+pub fn capture_screen(width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::ReadBuffer(gl::BACK);
+        gl::ReadPixels(
+            0,
+            0,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+
+    pixels
+}
+
+/// Write RGBA8 pixels read back from OpenGL (bottom row first) to a PNG at `path`,
+/// e.g. the output of `capture_screen` or `Framebuffer::read_pixels`
+pub fn save_screenshot(path: &str, width: u32, height: u32, pixels: Vec<u8>) -> Result<(), String> {
+    let mut image = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| format!("Pixel buffer doesn't match {}x{} RGBA", width, height))?;
+    // OpenGL's origin is bottom-left
+    image::imageops::flip_vertical_in_place(&mut image);
+    image
+        .save(path)
+        .map_err(|e| format!("Failed to save screenshot {}: {}", path, e))
+}
+
+/// A PNG file name in the working directory that won't clash with earlier captures,
+/// e.g. "screenshot_1700000000000.png"
+pub fn timestamped_path(prefix: &str) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("{}_{}.png", prefix, timestamp)
+}
@@ -1,21 +1,56 @@
-use crate::light::Light;
+use crate::light::{Light, LightKind};
 use crate::material::Material;
+use gl::types::{GLbitfield, GLenum};
 use nalgebra_glm as glm;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::ptr;
 
 /// Maximum number of lights supported by the shader
-pub const MAX_LIGHTS: usize = 4;
+/// Must match `MAX_LIGHTS` in basic.frag; extra lights passed to `set_lights` are ignored
+pub const MAX_LIGHTS: usize = 8;
 
 /// Manages a compiled and linked OpenGL shader program
 pub struct Shader {
     pub id: u32,                                  // OpenGL program ID
     uniform_cache: RefCell<HashMap<String, i32>>, // Cache for uniform locations
+    // Stage type and source file of each stage, kept so `reload` can rebuild the program
+    stages: Vec<(GLenum, String)>,
 }
 
+/// Why a shader program couldn't be built
+#[derive(Debug)]
+pub enum ShaderError {
+    FileRead(io::Error), // The message includes the file path
+    Compile {
+        stage: &'static str,
+        path: String,
+        log: String,
+    },
+    Link {
+        log: String,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::FileRead(e) => write!(f, "Failed to read shader: {}", e),
+            ShaderError::Compile { stage, path, log } => {
+                write!(f, "{} shader {} failed to compile:\n{}", stage, path, log)
+            }
+            ShaderError::Link { log } => write!(f, "Shader program linking failed:\n{}", log),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
 impl Shader {
     /// Creates a new shader program from vertex and fragment shader files
     ///
@@ -23,43 +58,183 @@ impl Shader {
     /// * `vertex_path` - Path to vertex shader file (e.g., "shaders/basic.vert")
     /// * `fragment_path` - Path to fragment shader file (e.g., "shaders/basic.frag")
     ///
-    /// # Panics
-    /// Panics if shader files can't be read or shaders fail to compile/link
-    pub fn new(vertex_path: &str, fragment_path: &str) -> Self {
-        // Read shader source files
-        let vertex_src = fs::read_to_string(vertex_path)
-            .expect(&format!("Failed to read vertex shader: {}", vertex_path));
+    /// # Errors
+    /// Returns an error if a file can't be read or a shader fails to compile/link
+    pub fn new(vertex_path: &str, fragment_path: &str) -> Result<Self, ShaderError> {
+        Self::from_stages(&[
+            (gl::VERTEX_SHADER, vertex_path),
+            (gl::FRAGMENT_SHADER, fragment_path),
+        ])
+    }
 
-        let fragment_src = fs::read_to_string(fragment_path).expect(&format!(
-            "Failed to read fragment shader: {}",
-            fragment_path
-        ));
+    /// Creates a shader program with a geometry stage between the vertex and fragment stages
+    ///
+    /// # Errors
+    /// Returns an error if a file can't be read or a shader fails to compile/link
+    pub fn with_geometry(
+        vertex_path: &str,
+        geometry_path: &str,
+        fragment_path: &str,
+    ) -> Result<Self, ShaderError> {
+        Self::from_stages(&[
+            (gl::VERTEX_SHADER, vertex_path),
+            (gl::GEOMETRY_SHADER, geometry_path),
+            (gl::FRAGMENT_SHADER, fragment_path),
+        ])
+    }
+
+    /// Creates a compute shader program from a single `.comp` file
+    /// Compute shaders need a GL 4.3+ context (the Linux default of 4.5 is fine, macOS is not)
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read or the shader fails to compile/link
+    #[allow(dead_code)]
+    pub fn new_compute(compute_path: &str) -> Result<Self, ShaderError> {
+        Self::from_stages(&[(gl::COMPUTE_SHADER, compute_path)])
+    }
 
-        println!("Compiling shader: {} {}", vertex_path, fragment_path);
+    fn from_stages(stages: &[(GLenum, &str)]) -> Result<Self, ShaderError> {
+        Ok(Shader {
+            id: Self::build_program(stages)?,
+            uniform_cache: RefCell::new(HashMap::new()),
+            stages: stages
+                .iter()
+                .map(|&(stage, path)| (stage, path.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Like `new`, but panics with the compile/link log on failure
+    /// For built-in shaders the app can't run without
+    pub fn new_or_panic(vertex_path: &str, fragment_path: &str) -> Self {
+        Self::new(vertex_path, fragment_path).unwrap_or_else(|e| panic!("{}", e))
+    }
 
+    /// Re-read the source files and rebuild the program
+    /// The old program is only replaced if the new one compiles and links, so a typo
+    /// while editing a shader leaves the last working version running
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        let stages: Vec<(GLenum, &str)> = self
+            .stages
+            .iter()
+            .map(|(stage, path)| (*stage, path.as_str()))
+            .collect();
+        let program = Self::build_program(&stages)?;
         unsafe {
-            // Compile shaders
-            let vertex_shader = Self::compile_shader(&vertex_src, gl::VERTEX_SHADER);
-            let fragment_shader = Self::compile_shader(&fragment_src, gl::FRAGMENT_SHADER);
+            gl::DeleteProgram(self.id);
+        }
+        self.id = program;
+        // Locations belong to the old program
+        self.uniform_cache.borrow_mut().clear();
+        Ok(())
+    }
+
+    /// The source files this program was built from, in stage order
+    pub fn source_paths(&self) -> Vec<&str> {
+        self.stages.iter().map(|(_, path)| path.as_str()).collect()
+    }
+
+    /// Compile each stage's source file and link them into a program
+    fn build_program(stages: &[(GLenum, &str)]) -> Result<u32, ShaderError> {
+        // Read shader source files
+        let sources = stages
+            .iter()
+            .map(|&(_, path)| Self::read_source(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let paths: Vec<&str> = stages.iter().map(|&(_, path)| path).collect();
+        println!("Compiling shader: {}", paths.join(" "));
+
+        unsafe {
+            // Compile shaders, cleaning up the finished ones if a later stage fails
+            let mut shaders = Vec::with_capacity(stages.len());
+            for (&(stage, path), source) in stages.iter().zip(&sources) {
+                match Self::compile_shader(source, stage, path) {
+                    Ok(shader) => shaders.push(shader),
+                    Err(e) => {
+                        for shader in shaders {
+                            gl::DeleteShader(shader);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
 
             // Link program
             let program = gl::CreateProgram();
-            gl::AttachShader(program, vertex_shader);
-            gl::AttachShader(program, fragment_shader);
+            for &shader in &shaders {
+                gl::AttachShader(program, shader);
+            }
             gl::LinkProgram(program);
 
+            // Clean up individual shaders (no longer needed after linking)
+            for shader in shaders {
+                gl::DeleteShader(shader);
+            }
+
             // Check for linking errors
-            Self::check_link_errors(program);
+            if let Err(e) = Self::check_link_errors(program) {
+                gl::DeleteProgram(program);
+                return Err(e);
+            }
 
-            // Clean up individual shaders (no longer needed after linking)
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
+            Ok(program)
+        }
+    }
+
+    /// Read a shader source file and splice in any `#include "file.glsl"` lines
+    /// Includes are resolved relative to the including file's directory and each file is
+    /// only pulled in once, so shared snippets can include each other without cycles
+    fn read_source(path: &str) -> Result<String, ShaderError> {
+        let path = Path::new(path);
+        let mut visited = HashSet::new();
+        visited.insert(Self::include_key(path));
+        Self::read_with_includes(path, &mut visited)
+    }
 
-            Shader {
-                id: program,
-                uniform_cache: RefCell::new(HashMap::new()),
+    fn read_with_includes(
+        path: &Path,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Result<String, ShaderError> {
+        // Keep the path in the error message
+        let source = fs::read_to_string(path).map_err(|e| {
+            ShaderError::FileRead(io::Error::new(
+                e.kind(),
+                format!("{}: {}", path.display(), e),
+            ))
+        })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut output = String::with_capacity(source.len());
+        for line in source.lines() {
+            match Self::parse_include(line) {
+                Some(name) => {
+                    let include_path = dir.join(name);
+                    if visited.insert(Self::include_key(&include_path)) {
+                        output.push_str(&Self::read_with_includes(&include_path, visited)?);
+                    }
+                }
+                None => {
+                    output.push_str(line);
+                    output.push('\n');
+                }
             }
         }
+        Ok(output)
+    }
+
+    /// The file name from an `#include "file.glsl"` line, if that's what this line is
+    fn parse_include(line: &str) -> Option<&str> {
+        line.trim()
+            .strip_prefix("#include")?
+            .trim()
+            .strip_prefix('"')?
+            .strip_suffix('"')
+    }
+
+    /// Identity of a file for the include visited-set ("a/../b.glsl" and "b.glsl" match)
+    fn include_key(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
     }
 
     /// Activates this shader program
@@ -69,6 +244,26 @@ impl Shader {
         }
     }
 
+    /// Run a compute shader over an `x * y * z` grid of work groups
+    /// Binds the program first, so uniforms set afterwards still apply to it
+    #[allow(dead_code)]
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.use_program();
+        unsafe {
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    /// Wait for compute writes to be visible to later reads, e.g.
+    /// `gl::SHADER_STORAGE_BARRIER_BIT | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT` before
+    /// drawing particles a dispatch just updated
+    #[allow(dead_code)]
+    pub fn memory_barrier(barriers: GLbitfield) {
+        unsafe {
+            gl::MemoryBarrier(barriers);
+        }
+    }
+
     /// Get a uniform location, using the cache if available
     fn get_uniform_location(&self, name: &str) -> i32 {
         // Try to get from cache first
@@ -85,7 +280,8 @@ impl Shader {
             gl::GetUniformLocation(self.id, c_name.as_ptr())
         };
 
-        // Store in cache
+        // Store in cache, including -1 (not found or optimized out by the compiler)
+        // so dead uniforms aren't re-queried every frame; glUniform* ignores -1
         self.uniform_cache
             .borrow_mut()
             .insert(name.to_string(), location);
@@ -114,6 +310,22 @@ impl Shader {
         }
     }
 
+    #[allow(dead_code)]
+    pub fn set_vec4(&self, name: &str, value: &glm::Vec4) {
+        let location = self.get_uniform_location(name);
+        unsafe {
+            gl::Uniform4f(location, value.x, value.y, value.z, value.w);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_mat3(&self, name: &str, matrix: &glm::Mat3) {
+        let location = self.get_uniform_location(name);
+        unsafe {
+            gl::UniformMatrix3fv(location, 1, gl::FALSE, matrix.as_ptr());
+        }
+    }
+
     pub fn set_float(&self, name: &str, value: f32) {
         let location = self.get_uniform_location(name);
         unsafe {
@@ -128,6 +340,22 @@ impl Shader {
         }
     }
 
+    pub fn set_float_array(&self, name: &str, values: &[f32]) {
+        let location = self.get_uniform_location(name);
+        unsafe {
+            gl::Uniform1fv(location, values.len() as i32, values.as_ptr());
+        }
+    }
+
+    /// Set an int array uniform, e.g. a `sampler2D[]` bound to several texture units
+    #[allow(dead_code)]
+    pub fn set_int_array(&self, name: &str, values: &[i32]) {
+        let location = self.get_uniform_location(name);
+        unsafe {
+            gl::Uniform1iv(location, values.len() as i32, values.as_ptr());
+        }
+    }
+
     pub fn set_bool(&self, name: &str, value: bool) {
         self.set_int(name, value as i32);
     }
@@ -137,6 +365,7 @@ impl Shader {
         self.set_vec3("material_diffuse", &material.diffuse);
         self.set_vec3("material_specular", &material.specular);
         self.set_float("material_shininess", material.shininess);
+        self.set_vec3("material_emissive", &material.emissive);
     }
 
     pub fn set_light(&self, index: usize, light: &Light) {
@@ -146,15 +375,30 @@ impl Shader {
         self.set_float(&format!("{}.constant", base), light.constant);
         self.set_float(&format!("{}.linear", base), light.linear);
         self.set_float(&format!("{}.quadratic", base), light.quadratic);
+
+        // Point lights get a cone wider than a full sphere, so the spot factor is always 1
+        let (direction, cut_off, outer_cut_off) = match light.kind {
+            LightKind::Point => (glm::vec3(0.0, -1.0, 0.0), -1.0, -2.0),
+            LightKind::Spot {
+                direction,
+                inner_cutoff,
+                outer_cutoff,
+            } => (
+                direction,
+                inner_cutoff.to_radians().cos(),
+                outer_cutoff.to_radians().cos(),
+            ),
+        };
+        self.set_vec3(&format!("{}.direction", base), &direction);
+        self.set_float(&format!("{}.cutOff", base), cut_off);
+        self.set_float(&format!("{}.outerCutOff", base), outer_cut_off);
     }
 
-    /// Sets all lights from a slice
+    /// Sets all lights from a slice (only the first `MAX_LIGHTS` are uploaded)
     pub fn set_lights(&self, lights: &[Light]) {
-        self.set_int("numLights", lights.len() as i32);
-        for (i, light) in lights.iter().enumerate() {
-            if i >= MAX_LIGHTS {
-                break;
-            }
+        let count = lights.len().min(MAX_LIGHTS);
+        self.set_int("numLights", count as i32);
+        for (i, light) in lights.iter().take(count).enumerate() {
             self.set_light(i, light);
         }
     }
@@ -162,20 +406,31 @@ impl Shader {
     /// Compiles a shader from source code
     ///
     /// Private helper function (no `pub` keyword)
-    unsafe fn compile_shader(source: &str, shader_type: gl::types::GLenum) -> u32 {
+    unsafe fn compile_shader(
+        source: &str,
+        shader_type: GLenum,
+        path: &str,
+    ) -> Result<u32, ShaderError> {
         let shader = gl::CreateShader(shader_type);
         let c_str = CString::new(source.as_bytes()).unwrap();
         gl::ShaderSource(shader, 1, &c_str.as_ptr(), ptr::null());
         gl::CompileShader(shader);
 
         // Check for compilation errors
-        Self::check_compile_errors(shader, shader_type);
+        if let Err(e) = Self::check_compile_errors(shader, shader_type, path) {
+            gl::DeleteShader(shader);
+            return Err(e);
+        }
 
-        shader
+        Ok(shader)
     }
 
-    /// Checks for shader compilation errors
-    unsafe fn check_compile_errors(shader: u32, shader_type: gl::types::GLenum) {
+    /// Checks for shader compilation errors, returning the info log on failure
+    unsafe fn check_compile_errors(
+        shader: u32,
+        shader_type: GLenum,
+        path: &str,
+    ) -> Result<(), ShaderError> {
         let mut success = 0;
         gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
 
@@ -186,22 +441,24 @@ impl Shader {
             let mut buffer = vec![0u8; len as usize];
             gl::GetShaderInfoLog(shader, len, ptr::null_mut(), buffer.as_mut_ptr() as *mut i8);
 
-            let shader_type_str = if shader_type == gl::VERTEX_SHADER {
-                "VERTEX"
-            } else {
-                "FRAGMENT"
+            let shader_type_str = match shader_type {
+                gl::VERTEX_SHADER => "VERTEX",
+                gl::GEOMETRY_SHADER => "GEOMETRY",
+                gl::COMPUTE_SHADER => "COMPUTE",
+                _ => "FRAGMENT",
             };
 
-            panic!(
-                "{} shader compilation failed:\n{}",
-                shader_type_str,
-                String::from_utf8_lossy(&buffer)
-            );
+            return Err(ShaderError::Compile {
+                stage: shader_type_str,
+                path: path.to_string(),
+                log: String::from_utf8_lossy(&buffer).into_owned(),
+            });
         }
+        Ok(())
     }
 
-    /// Checks for program linking errors
-    unsafe fn check_link_errors(program: u32) {
+    /// Checks for program linking errors, returning the info log on failure
+    unsafe fn check_link_errors(program: u32) -> Result<(), ShaderError> {
         let mut success = 0;
         gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
 
@@ -217,11 +474,11 @@ impl Shader {
                 buffer.as_mut_ptr() as *mut i8,
             );
 
-            panic!(
-                "Shader program linking failed:\n{}",
-                String::from_utf8_lossy(&buffer)
-            );
+            return Err(ShaderError::Link {
+                log: String::from_utf8_lossy(&buffer).into_owned(),
+            });
         }
+        Ok(())
     }
 }
 
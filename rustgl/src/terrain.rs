@@ -1,6 +1,43 @@
 use crate::mesh::Mesh;
 use crate::mesh::Vertex;
 use crate::noise::PerlinNoise;
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul};
+
+/// Noise function used to build the height field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoiseType {
+    /// Fractal Brownian motion: rolling hills, heights in -1..1 before `height_scale`
+    Fbm,
+    /// Ridged multifractal: sharp mountain crests, heights in 0..1 before `height_scale`
+    Ridged,
+}
+
+// Corners of the grid cell around a sample point and the position inside it
+struct GridCell {
+    x0: usize,
+    z0: usize,
+    x1: usize,
+    z1: usize,
+    fx: f32,
+    fz: f32,
+}
+
+impl GridCell {
+    // Bilinear interpolation of a per-vertex value, `value(x, z)` (a height, a normal, ...)
+    fn interpolate<T>(&self, value: impl Fn(usize, usize) -> T) -> T
+    where
+        T: Add<Output = T> + Mul<f32, Output = T>,
+    {
+        // Interpolate along x
+        let v0 = value(self.x0, self.z0) * (1.0 - self.fx) + value(self.x1, self.z0) * self.fx;
+        let v1 = value(self.x0, self.z1) * (1.0 - self.fx) + value(self.x1, self.z1) * self.fx;
+
+        // Interpolate along z
+        v0 * (1.0 - self.fz) + v1 * self.fz
+    }
+}
 
 pub struct Terrain {
     // width of terrain in world units
@@ -21,10 +58,19 @@ pub struct Terrain {
     pub persistence: f32,
     pub lacunarity: f32,
     pub noise_scale: f32,
+    pub noise_type: NoiseType,
+    // Domain warp applied before the noise (0.0 = off), see `PerlinNoise::warp`
+    pub warp_strength: f32,
 
     // Height scale multiplier
     pub height_scale: f32,
 
+    // Surface colors: flat ground is grass, ground steeper than the threshold is rock
+    // Only used by `create_mesh`, so changing them doesn't need `regenerate`
+    pub grass_color: [f32; 3],
+    pub rock_color: [f32; 3],
+    pub slope_threshold: f32, // Slope (1.0 - normal.y) where rock takes over, 0.3 is about 45 degrees
+
     // Stored heights for collision detection [z][x]
     heights: Vec<Vec<f32>>,
 }
@@ -52,7 +98,12 @@ impl Terrain {
             persistence,
             lacunarity,
             noise_scale,
+            noise_type: NoiseType::Fbm,
+            warp_strength: 0.0,
             height_scale,
+            grass_color: [0.3, 0.55, 0.2],
+            rock_color: [0.45, 0.42, 0.4],
+            slope_threshold: 0.3,
             heights: Vec::new(),
         }
     }
@@ -85,9 +136,19 @@ impl Terrain {
                 let world_x = (x as f32 * step_x) - (self.width / 2.0);
                 let world_z = (z as f32 * step_z) - (self.depth / 2.0);
 
-                let noise_value = perlin.fractal_noise(
+                let noise = match self.noise_type {
+                    NoiseType::Fbm => PerlinNoise::fractal_noise,
+                    NoiseType::Ridged => PerlinNoise::ridged_noise,
+                };
+                let (noise_x, noise_z) = perlin.warp(
                     world_x * self.noise_scale,
                     world_z * self.noise_scale,
+                    self.warp_strength,
+                );
+                let noise_value = noise(
+                    &perlin,
+                    noise_x,
+                    noise_z,
                     self.octaves,
                     self.persistence,
                     self.lacunarity,
@@ -119,17 +180,24 @@ impl Terrain {
 
                 let height = self.heights[z][x];
 
-                // Color based on height
-                let color_value = (height / self.height_scale + 1.0) * 0.5; // map back to 0..1
+                let normal = self.grid_normal(x, z, step_x, step_z);
+
+                // Grass on flat ground, rock on steep slopes, blended over a short band
+                let slope = 1.0 - normal[1];
+                let rock = smoothstep(
+                    self.slope_threshold - 0.1,
+                    self.slope_threshold + 0.1,
+                    slope,
+                );
 
-                let color = [
-                    color_value * 0.5 + 0.2, // R: brownish
-                    color_value * 0.7 + 0.3, // G: greenish
-                    color_value * 0.3 + 0.1, // B: less blue
-                ];
+                // Height tint on top: valleys darker, peaks lighter
+                let color_value = (height / self.height_scale + 1.0) * 0.5; // map back to 0..1
+                let brightness = 0.6 + 0.8 * color_value;
 
-                // Temporary normal.
-                let normal = [0.0, 1.0, 0.0];
+                let color = [0, 1, 2].map(|i| {
+                    (self.grass_color[i] + (self.rock_color[i] - self.grass_color[i]) * rock)
+                        * brightness
+                });
 
                 let u = x as f32 / self.resolution_x as f32;
                 let v = z as f32 / self.resolution_z as f32;
@@ -166,7 +234,51 @@ impl Terrain {
         Mesh::new_indexed(&vertices, &indices)
     }
 
+    // Surface normal at a grid vertex from the slope of the height field
+    // Central differences inside the grid, forward/backward differences on the edges
+    fn grid_normal(&self, x: usize, z: usize, step_x: f32, step_z: f32) -> [f32; 3] {
+        let x0 = x.saturating_sub(1);
+        let x1 = (x + 1).min(self.resolution_x);
+        let z0 = z.saturating_sub(1);
+        let z1 = (z + 1).min(self.resolution_z);
+
+        // Height change per world unit along each axis
+        let dh_dx = (self.heights[z][x1] - self.heights[z][x0]) / ((x1 - x0) as f32 * step_x);
+        let dh_dz = (self.heights[z1][x] - self.heights[z0][x]) / ((z1 - z0) as f32 * step_z);
+
+        // cross(tangent_z, tangent_x) with tangent_x = (1, dh_dx, 0), tangent_z = (0, dh_dz, 1)
+        let normal = glm::normalize(&glm::vec3(-dh_dx, 1.0, -dh_dz));
+        [normal.x, normal.y, normal.z]
+    }
+
     pub fn sample_height(&self, world_x: f32, world_z: f32) -> Option<f32> {
+        let cell = self.grid_cell(world_x, world_z)?;
+        Some(cell.interpolate(|x, z| self.heights[z][x]))
+    }
+
+    /// Interpolated surface normal, matching the normals baked into the mesh,
+    /// e.g. for aligning objects to the ground. None outside the terrain
+    #[allow(dead_code)]
+    pub fn sample_normal(&self, world_x: f32, world_z: f32) -> Option<glm::Vec3> {
+        self.sample_height_and_normal(world_x, world_z)
+            .map(|(_, normal)| normal)
+    }
+
+    /// `sample_height` and `sample_normal` in one go, sharing the grid lookup
+    #[allow(dead_code)]
+    pub fn sample_height_and_normal(&self, world_x: f32, world_z: f32) -> Option<(f32, glm::Vec3)> {
+        let cell = self.grid_cell(world_x, world_z)?;
+        let step_x = self.width / self.resolution_x as f32;
+        let step_z = self.depth / self.resolution_z as f32;
+
+        let height = cell.interpolate(|x, z| self.heights[z][x]);
+        let normal =
+            cell.interpolate(|x, z| glm::Vec3::from(self.grid_normal(x, z, step_x, step_z)));
+        Some((height, glm::normalize(&normal)))
+    }
+
+    // The grid cell containing a world position, or None outside the terrain
+    fn grid_cell(&self, world_x: f32, world_z: f32) -> Option<GridCell> {
         // Convert world position to local terrain coordinates
         let local_x = world_x + (self.width / 2.0);
         let local_z = world_z + (self.depth / 2.0);
@@ -183,27 +295,16 @@ impl Terrain {
         // Get integer grid cell
         let x0 = grid_x.floor() as usize;
         let z0 = grid_z.floor() as usize;
-        let x1 = (x0 + 1).min(self.resolution_x);
-        let z1 = (z0 + 1).min(self.resolution_z);
 
-        // get fractional part for interpolation
-        let fx = grid_x - x0 as f32;
-        let fz = grid_z - z0 as f32;
-
-        // Bilinear interpolation of height scales
-        let h00 = self.heights[z0][x0];
-        let h10 = self.heights[z0][x1];
-        let h01 = self.heights[z1][x0];
-        let h11 = self.heights[z1][x1];
-
-        // Interpolate along x
-        let h0 = h00 * (1.0 - fx) + h10 * fx;
-        let h1 = h01 * (1.0 - fx) + h11 * fx;
-
-        // Interpolate along z
-        let height = h0 * (1.0 - fz) + h1 * fz;
-
-        Some(height)
+        Some(GridCell {
+            x0,
+            z0,
+            x1: (x0 + 1).min(self.resolution_x),
+            z1: (z0 + 1).min(self.resolution_z),
+            // get fractional part for interpolation
+            fx: grid_x - x0 as f32,
+            fz: grid_z - z0 as f32,
+        })
     }
 
     /// Get terrain dimensions
@@ -231,3 +332,75 @@ impl Terrain {
         self.regenerate();
     }
 }
+
+// Hermite interpolation from 0.0 at `edge0` to 1.0 at `edge1`, like GLSL's smoothstep
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 8.0;
+    const RESOLUTION: usize = 4;
+
+    // Terrain whose height field is `height(world_x, world_z)` sampled at the grid vertices
+    fn terrain_with_heights(height: impl Fn(f32, f32) -> f32) -> Terrain {
+        let mut terrain = Terrain::with_defaults(SIZE, SIZE, RESOLUTION);
+        let step = SIZE / RESOLUTION as f32;
+        terrain.heights = (0..=RESOLUTION)
+            .map(|z| {
+                (0..=RESOLUTION)
+                    .map(|x| height(x as f32 * step, z as f32 * step))
+                    .collect()
+            })
+            .collect();
+        terrain
+    }
+
+    // Every grid vertex, edges and corners included
+    fn all_normals(terrain: &Terrain) -> Vec<[f32; 3]> {
+        let step = SIZE / RESOLUTION as f32;
+        let mut normals = Vec::new();
+        for z in 0..=RESOLUTION {
+            for x in 0..=RESOLUTION {
+                normals.push(terrain.grid_normal(x, z, step, step));
+            }
+        }
+        normals
+    }
+
+    fn assert_close(actual: [f32; 3], expected: glm::Vec3) {
+        for axis in 0..3 {
+            assert!(
+                (actual[axis] - expected[axis]).abs() < 1e-5,
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn flat_height_field_has_upward_normals() {
+        let terrain = terrain_with_heights(|_, _| 3.0);
+        for normal in all_normals(&terrain) {
+            assert_close(normal, glm::vec3(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn ramp_has_analytic_normals() {
+        // h = 0.5x - 0.25z, so the normal is normalize(-dh/dx, 1, -dh/dz)
+        let terrain = terrain_with_heights(|x, z| 0.5 * x - 0.25 * z);
+        let expected = glm::normalize(&glm::vec3(-0.5, 1.0, 0.25));
+        for normal in all_normals(&terrain) {
+            assert_close(normal, expected);
+        }
+
+        let (_, sampled) = terrain.sample_height_and_normal(1.3, -2.7).unwrap();
+        assert_close(sampled.into(), expected);
+    }
+}
@@ -1,12 +1,84 @@
+use crate::gpu_memory::{self, GpuResource};
 use gl::types::*;
+use image::codecs::hdr::HdrDecoder;
 use image::GenericImageView;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
+use std::sync::Once;
+
+// GL_ARB/EXT_texture_filter_anisotropic (core in 4.6, so missing from the 4.5 bindings)
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
 
 pub enum TextureType {
     Texture2D,
     Cubemap,
 }
 
+/// Sampling state for a 2D texture, given as GL enums (e.g. `gl::CLAMP_TO_EDGE`, `gl::NEAREST`)
+#[derive(Debug, Clone, Copy)]
+pub struct TextureOptions {
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub min_filter: GLenum,
+    pub mag_filter: GLenum,
+    // Without mipmaps a mipmap min filter falls back to its base filter
+    // (LINEAR_MIPMAP_LINEAR -> LINEAR), since it would otherwise sample as black
+    pub generate_mipmaps: bool,
+    // Max anisotropic filtering samples (1.0 = off), clamped to what the driver supports
+    // Sharpens surfaces seen at grazing angles, like the ground plane
+    pub anisotropy: f32,
+    // Color data authored in sRGB (albedo/diffuse maps, lens dirt, anything painted or
+    // photographed): stored as SRGB8_ALPHA8 so sampling returns linear values for lighting.
+    // Leave false for data textures (normal, roughness/metallic and height maps, masks),
+    // which are already linear and would be skewed darker by the conversion
+    pub srgb: bool,
+}
+
+impl Default for TextureOptions {
+    /// Repeating, trilinear filtered, mipmapped
+    fn default() -> Self {
+        TextureOptions {
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            min_filter: gl::LINEAR_MIPMAP_LINEAR,
+            mag_filter: gl::LINEAR,
+            generate_mipmaps: true,
+            anisotropy: 1.0,
+            srgb: false,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// The min filter to actually use: mipmap filters need a mip chain to sample from
+    fn effective_min_filter(&self) -> GLenum {
+        if self.generate_mipmaps {
+            return self.min_filter;
+        }
+        match self.min_filter {
+            gl::NEAREST_MIPMAP_NEAREST | gl::NEAREST_MIPMAP_LINEAR => gl::NEAREST,
+            gl::LINEAR_MIPMAP_NEAREST | gl::LINEAR_MIPMAP_LINEAR => gl::LINEAR,
+            filter => filter,
+        }
+    }
+
+    /// Clamped, nearest filtered, no mipmaps: keeps pixel-art texels crisp
+    #[allow(dead_code)]
+    pub fn pixel_art() -> Self {
+        TextureOptions {
+            wrap_s: gl::CLAMP_TO_EDGE,
+            wrap_t: gl::CLAMP_TO_EDGE,
+            min_filter: gl::NEAREST,
+            mag_filter: gl::NEAREST,
+            generate_mipmaps: false,
+            anisotropy: 1.0,
+            srgb: false,
+        }
+    }
+}
+
 pub struct Texture {
     pub id: GLuint,
     #[allow(dead_code)]
@@ -14,10 +86,20 @@ pub struct Texture {
     #[allow(dead_code)]
     pub height: u32,
     pub texture_type: TextureType,
+    #[allow(dead_code)]
+    pub internal_format: GLenum, // e.g. RGB16F for HDR textures, whose samples exceed 1.0
+    gpu_bytes: usize, // Uploaded size, reported to the GPU memory tally
 }
 
 impl Texture {
+    /// Load a 2D texture with the default options (repeat, trilinear, mipmapped)
+    #[allow(dead_code)]
     pub fn new(path: &str) -> Result<Self, String> {
+        Self::new_with_options(path, TextureOptions::default())
+    }
+
+    /// Load a 2D texture with custom wrapping and filtering
+    pub fn new_with_options(path: &str, options: TextureOptions) -> Result<Self, String> {
         // 1. Load image from disk
         let img = image::open(Path::new(path))
             .map_err(|e| format!("Failed to load texture {}: {}", path, e))?;
@@ -25,7 +107,71 @@ impl Texture {
         // 2. Convert to RGBA8 format (required by OpenGL)
         let img = img.to_rgba8();
         let (width, height) = img.dimensions();
-        let data = img.into_raw();
+        Ok(Self::upload_rgba(width, height, &img.into_raw(), options))
+    }
+
+    /// Upload an RGBA8 buffer from memory (e.g. a generated noise map) with the same
+    /// sampling setup as `new`
+    /// `data` holds `width * height` texels, 4 bytes each, bottom row first
+    #[allow(dead_code)]
+    pub fn from_rgba(width: u32, height: u32, data: &[u8]) -> Result<Self, String> {
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(format!(
+                "Texture data is {} bytes, expected {} for {}x{} RGBA",
+                data.len(),
+                expected,
+                width,
+                height
+            ));
+        }
+        Ok(Self::upload_rgba(
+            width,
+            height,
+            data,
+            TextureOptions::default(),
+        ))
+    }
+
+    /// A 1x1 texture of a single color, e.g. a neutral default for a material map
+    #[allow(dead_code)]
+    pub fn solid_color(rgba: [u8; 4]) -> Self {
+        Self::upload_rgba(1, 1, &rgba, TextureOptions::default())
+    }
+
+    /// A `size` x `size` checkerboard with `tiles` squares along each edge, starting with
+    /// `color_a` in the corner; handy for checking UVs
+    /// Nearest filtered so the squares keep hard edges up close
+    pub fn checkerboard(size: u32, tiles: u32, color_a: [u8; 4], color_b: [u8; 4]) -> Self {
+        let size = size.max(1);
+        let tile_size = (size / tiles.max(1)).max(1);
+        let mut data = Vec::with_capacity(size as usize * size as usize * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let color = if (x / tile_size + y / tile_size).is_multiple_of(2) {
+                    color_a
+                } else {
+                    color_b
+                };
+                data.extend_from_slice(&color);
+            }
+        }
+
+        let options = TextureOptions {
+            min_filter: gl::NEAREST_MIPMAP_LINEAR,
+            mag_filter: gl::NEAREST,
+            ..Default::default()
+        };
+        Self::upload_rgba(size, size, &data, options)
+    }
+
+    /// Upload tightly packed RGBA8 pixels, `width * height * 4` bytes
+    fn upload_rgba(width: u32, height: u32, data: &[u8], options: TextureOptions) -> Self {
+        let internal_format = if options.srgb {
+            gl::SRGB8_ALPHA8
+        } else {
+            gl::RGBA8
+        };
 
         // 3. Generate OpenGL texture
         let mut id: GLuint = 0;
@@ -35,9 +181,9 @@ impl Texture {
 
             // 4. Upload pixel data to GPU
             gl::TexImage2D(
-                gl::TEXTURE_2D,    // Target
-                0,                 // Mipmap level (0 = base)
-                gl::RGBA as GLint, // Internal format
+                gl::TEXTURE_2D,           // Target
+                0,                        // Mipmap level (0 = base)
+                internal_format as GLint, // Internal format
                 width as GLint,
                 height as GLint,
                 0,                         // Border (must be 0)
@@ -47,31 +193,122 @@ impl Texture {
             );
 
             // 5. Set texture parameters
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, options.wrap_s as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, options.wrap_t as GLint);
             gl::TexParameteri(
                 gl::TEXTURE_2D,
                 gl::TEXTURE_MIN_FILTER,
-                gl::LINEAR_MIPMAP_LINEAR as GLint,
+                options.effective_min_filter() as GLint,
             );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAG_FILTER,
+                options.mag_filter as GLint,
+            );
+            set_anisotropy(gl::TEXTURE_2D, options.anisotropy);
 
             // 6. Generate mipmaps
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+            if options.generate_mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
+
+        // RGBA8 base level, plus roughly a third more for the mip chain
+        let mut gpu_bytes = width as usize * height as usize * 4;
+        if options.generate_mipmaps {
+            gpu_bytes = gpu_bytes * 4 / 3;
         }
+        gpu_memory::track_alloc(GpuResource::Texture, gpu_bytes);
+
+        Texture {
+            id,
+            width,
+            height,
+            texture_type: TextureType::Texture2D,
+            internal_format,
+            gpu_bytes,
+        }
+    }
+
+    /// Load a floating-point image (Radiance .hdr or OpenEXR) as an RGB16F texture,
+    /// e.g. an environment map for image-based lighting
+    /// Linear filtering with clamped edges and no mipmaps; values are kept above 1.0
+    #[allow(dead_code)]
+    pub fn new_hdr(path: &str) -> Result<Self, String> {
+        let (width, height, data) = Self::load_float_rgb(path)
+            .map_err(|e| format!("Failed to load HDR texture {}: {}", path, e))?;
+
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as GLint,
+                width as GLint,
+                height as GLint,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                data.as_ptr() as *const _,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        }
+
+        // Three half floats per texel
+        let gpu_bytes = width as usize * height as usize * 6;
+        gpu_memory::track_alloc(GpuResource::Texture, gpu_bytes);
 
         Ok(Texture {
             id,
             width,
             height,
             texture_type: TextureType::Texture2D,
+            internal_format: gl::RGB16F,
+            gpu_bytes,
         })
     }
 
+    /// Decode an image to RGB f32 texels: (width, height, data)
+    fn load_float_rgb(path: &str) -> image::ImageResult<(u32, u32, Vec<f32>)> {
+        // image's generic .hdr path tone maps down to 8 bits, so decode Radiance files directly
+        if Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("hdr"))
+        {
+            let file = BufReader::new(File::open(path)?);
+            let decoder = HdrDecoder::new(file)?;
+            let meta = decoder.metadata();
+            let data = decoder
+                .read_image_hdr()?
+                .into_iter()
+                .flat_map(|pixel| pixel.0)
+                .collect();
+            return Ok((meta.width, meta.height, data));
+        }
+
+        let img = image::open(path)?.to_rgb32f();
+        let (width, height) = img.dimensions();
+        Ok((width, height, img.into_raw()))
+    }
+
     /// Load a cubemap texture from 6 separate image files
     /// Order: right, left, top, bottom, front, back (+X, -X, +Y, -Y, +Z, -Z)
     pub fn new_cubemap(faces: [&str; 6]) -> Result<Self, String> {
         let mut texture_id = 0;
+        let mut gpu_bytes = 0;
 
         unsafe {
             gl::GenTextures(1, &mut texture_id);
@@ -85,12 +322,13 @@ impl Texture {
                 // Don't flip cubemap textures - they're already in the correct orientation
                 let data = img.to_rgb8();
                 let (width, height) = img.dimensions();
+                gpu_bytes += width as usize * height as usize * 3;
 
                 // GL_TEXTURE_CUBE_MAP_POSITIVE_X + i gives us each face
                 gl::TexImage2D(
                     gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
                     0,
-                    gl::RGB as i32,
+                    gl::SRGB8 as i32, // Sky images are color data, linearized on sample
                     width as i32,
                     height as i32,
                     0,
@@ -130,11 +368,15 @@ impl Texture {
             gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
         }
 
+        gpu_memory::track_alloc(GpuResource::Texture, gpu_bytes);
+
         Ok(Texture {
             id: texture_id,
             width: 0, // Not really relevant for cubemaps
             height: 0,
             texture_type: TextureType::Cubemap,
+            internal_format: gl::SRGB8,
+            gpu_bytes,
         })
     }
 
@@ -149,8 +391,36 @@ impl Texture {
     }
 }
 
+/// Set the anisotropic filtering level on the bound texture, clamped to the driver's max
+/// Does nothing when the level is 1.0 or lower, or the extension isn't available (logged
+/// the first time it's requested)
+unsafe fn set_anisotropy(target: GLenum, anisotropy: f32) {
+    static UNAVAILABLE_LOGGED: Once = Once::new();
+
+    if anisotropy <= 1.0 {
+        return;
+    }
+    if !(gpu_memory::has_extension("GL_ARB_texture_filter_anisotropic")
+        || gpu_memory::has_extension("GL_EXT_texture_filter_anisotropic"))
+    {
+        UNAVAILABLE_LOGGED.call_once(|| {
+            eprintln!("Anisotropic filtering is not supported by this driver, skipping it");
+        });
+        return;
+    }
+
+    let mut max_anisotropy = 1.0;
+    gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+    gl::TexParameterf(
+        target,
+        TEXTURE_MAX_ANISOTROPY,
+        anisotropy.min(max_anisotropy),
+    );
+}
+
 impl Drop for Texture {
     fn drop(&mut self) {
+        gpu_memory::track_free(GpuResource::Texture, self.gpu_bytes);
         unsafe {
             gl::DeleteTextures(1, &self.id);
         }